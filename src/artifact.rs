@@ -0,0 +1,237 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::error::{Error, Result};
+use crate::git_client::GitClient;
+
+/// Manifest at the root of a tarball artifact naming the version it contains.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// What a tarball's `manifest.json` says about its contents.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Manifest {
+    pub tag: String,
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// A `git bundle` file; its refs are imported into the local mirror.
+    Bundle,
+    /// A `.tar.gz` of the config with a `manifest.json` at its root.
+    Tarball,
+}
+
+/// A local file to deploy from instead of `repo_url`, for hosts without a route
+/// to the git server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+}
+
+impl Artifact {
+    /// Opens `path` as a bundle or tarball, going by its header rather than its name.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut header = [0u8; 16];
+        let read = {
+            use std::io::Read;
+            let mut file = std::fs::File::open(path).map_err(|e| Error::io(path, e))?;
+            file.read(&mut header).map_err(|e| Error::io(path, e))?
+        };
+        let header = &header[..read];
+        let kind =
+            if header.starts_with(b"# v2 git bundle") || header.starts_with(b"# v3 git bundle") {
+                ArtifactKind::Bundle
+            } else if header.starts_with(&[0x1f, 0x8b]) {
+                ArtifactKind::Tarball
+            } else {
+                return Err(Error::artifact(
+                    path,
+                    "not a git bundle or a gzip-compressed tarball",
+                ));
+            };
+        Ok(Self {
+            path: path.to_path_buf(),
+            kind,
+        })
+    }
+
+    /// Reads `manifest.json` from a tarball without unpacking it.
+    pub fn manifest(&self) -> Result<Manifest> {
+        let listing = self.tar(&["-tzf"], &[])?;
+        let member = listing
+            .lines()
+            .find(|name| name.trim_start_matches("./") == MANIFEST_FILE)
+            .ok_or_else(|| {
+                Error::artifact(&self.path, format!("no {} at the root", MANIFEST_FILE))
+            })?
+            .to_string();
+        let content = self.tar(&["-xzOf"], &[&member])?;
+        Self::parse_manifest(&self.path, &content)
+    }
+
+    fn parse_manifest(path: &Path, content: &str) -> Result<Manifest> {
+        let manifest: Manifest = serde_json::from_str(content)
+            .map_err(|e| Error::artifact(path, format!("invalid {}: {}", MANIFEST_FILE, e)))?;
+        let tag = manifest.tag.as_str();
+        if tag.is_empty() || tag == "." || tag == ".." || tag.contains('/') {
+            return Err(Error::artifact(
+                path,
+                format!("manifest tag '{}' is not a valid directory name", tag),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// The `tar` command that unpacks this tarball into `dir`.
+    pub fn unpack_args(&self, dir: &str) -> Vec<String> {
+        vec![
+            "tar".to_string(),
+            "-xzf".to_string(),
+            self.path.display().to_string(),
+            "-C".to_string(),
+            dir.to_string(),
+        ]
+    }
+
+    /// Unpacks this tarball into `traefik-config-<tag>` under `base_path`, unless that
+    /// version is already there, and returns its manifest. The files are extracted into
+    /// a staging directory first, so a failed unpack never leaves a partial version.
+    pub fn unpack(&self, base_path: &str) -> Result<Manifest> {
+        let manifest = self.manifest()?;
+        let versioned_path = GitClient::versioned_path(base_path, &manifest.tag);
+        if Path::new(&versioned_path).exists() {
+            info!(
+                "Version {} is already unpacked at {}",
+                manifest.tag, versioned_path
+            );
+            return Ok(manifest);
+        }
+
+        let staging = format!("{}/.unpack.{}", base_path, std::process::id());
+        if Path::new(&staging).exists() {
+            std::fs::remove_dir_all(&staging).map_err(|e| Error::io(&staging, e))?;
+        }
+        std::fs::create_dir_all(&staging).map_err(|e| Error::io(&staging, e))?;
+
+        info!("Unpacking {} to {}", self.path.display(), versioned_path);
+        let args = self.unpack_args(&staging);
+        let unpacked = std::process::Command::new("tar")
+            .args(&args[1..])
+            .output()
+            .map_err(|e| Error::io("tar", e))
+            .and_then(|output| {
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(Error::artifact(
+                        &self.path,
+                        format!(
+                            "tar failed: {}",
+                            String::from_utf8_lossy(&output.stderr).trim()
+                        ),
+                    ))
+                }
+            })
+            .and_then(|()| {
+                std::fs::rename(&staging, &versioned_path)
+                    .map_err(|e| Error::io(&versioned_path, e))
+            });
+        if let Err(e) = unpacked {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        Ok(manifest)
+    }
+
+    /// Runs `tar <flags> <artifact> <members>` and returns its stdout.
+    fn tar(&self, flags: &[&str], members: &[&str]) -> Result<String> {
+        let output = std::process::Command::new("tar")
+            .args(flags)
+            .arg(&self.path)
+            .args(members)
+            .output()
+            .map_err(|e| Error::io("tar", e))?;
+        if !output.status.success() {
+            return Err(Error::artifact(
+                &self.path,
+                format!(
+                    "tar failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn tarball(dir: &Path, manifest: &str) -> PathBuf {
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("dynamic")).unwrap();
+        std::fs::write(src.join("dynamic/routes.yml"), "http: {}\n").unwrap();
+        std::fs::write(src.join(MANIFEST_FILE), manifest).unwrap();
+        let archive = dir.join("config.tar.gz");
+        let status = std::process::Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&src)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        archive
+    }
+
+    #[test]
+    fn test_unpack_tarball_into_versioned_dir() {
+        let dir = tempdir().unwrap();
+        let archive = tarball(dir.path(), r#"{"tag": "v1.2.3", "commit": "abc123"}"#);
+        let base = dir.path().join("configs");
+        let base = base.to_str().unwrap();
+
+        let artifact = Artifact::open(&archive).unwrap();
+        assert_eq!(artifact.kind, ArtifactKind::Tarball);
+        let manifest = artifact.unpack(base).unwrap();
+        assert_eq!(
+            manifest,
+            Manifest {
+                tag: "v1.2.3".to_string(),
+                commit: "abc123".to_string()
+            }
+        );
+        let versioned = GitClient::versioned_path(base, "v1.2.3");
+        assert!(Path::new(&versioned).join("dynamic/routes.yml").exists());
+
+        // Unpacking again reuses the existing version
+        assert_eq!(artifact.unpack(base).unwrap(), manifest);
+        let leftovers: Vec<_> = std::fs::read_dir(base)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers.len(), 1, "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_rejects_bad_manifest_and_unknown_files() {
+        let dir = tempdir().unwrap();
+        let archive = tarball(dir.path(), r#"{"tag": "../escape", "commit": "abc"}"#);
+        let err = Artifact::open(&archive).unwrap().manifest().unwrap_err();
+        assert!(
+            err.to_string().contains("not a valid directory name"),
+            "{}",
+            err
+        );
+
+        let other = dir.path().join("notes.txt");
+        std::fs::write(&other, "hello").unwrap();
+        assert_eq!(Artifact::open(&other).unwrap_err().exit_code(), 2);
+    }
+}
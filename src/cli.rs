@@ -1,52 +1,511 @@
-use crate::config::Config;
+use crate::artifact::Artifact;
+use crate::config::{Config, Strategy};
 use crate::deployment_manager::DeploymentManager;
-use clap::Parser;
+use crate::error::{Error, Result};
+use crate::git_client::{redact_url, GitClient};
+use crate::history::{self, HistoryEntry};
+use crate::plan::DeployPlan;
+use crate::project_file::{ProjectFile, ProjectSettings, DEFAULT_PROJECT_FILES};
+use crate::route_diff::RouteDiff;
+use crate::status::StatusReport;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tracing::{debug, error, info};
 use tracing_subscriber;
 
-#[derive(Parser)]
+/// Placeholder default for `--repo-url`; treated as unset when a project file provides one.
+pub const DEFAULT_REPO_URL: &str = "https://bitbucket.org:financialpayments/plain-jane-proxy.git";
+
+#[derive(Parser, Clone)]
 pub struct CLI {
-    #[arg(value_name = "TAG", index = 1)]
-    pub tag: String,
-    #[arg(short, long)]
+    #[command(subcommand)]
+    pub command: Command,
+    #[arg(short, long, global = true)]
     pub name: Option<String>,
-    #[arg(short, long, default_value = "/var/run/docker.sock")]
+    #[arg(
+        long,
+        global = true,
+        value_name = "SUBSTRING",
+        help = "Image substring of the Traefik containers to roll [default: the project name]"
+    )]
+    pub image: Option<String>,
+    #[arg(short, long, global = true, default_value = "/var/run/docker.sock")]
     pub socket_path: String,
     #[arg(
         short,
         long,
-        default_value = "https://bitbucket.org:financialpayments/plain-jane-proxy.git"
+        global = true,
+        default_value = DEFAULT_REPO_URL
     )]
     pub repo_url: Option<String>,
-    #[arg(short, long, help = "Path to clone the config repo into")]
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Path to clone the config repo into"
+    )]
     pub clone_path: Option<String>,
     #[arg(
         long,
+        global = true,
         help = "Target path in the container to mount the config (e.g. /etc/traefik/dynamic)"
     )]
     pub mount_path: Option<String>,
-    #[arg(short, long, action = clap::ArgAction::Count, help = "Increase verbosity (-v, -vv, etc.)")]
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, help = "Increase verbosity (-v, -vv, etc.)")]
     pub verbose: u8,
-    #[arg(long, default_value = "docker-compose.yml")]
-    pub compose_file: String,
+    #[arg(
+        long = "compose-file",
+        global = true,
+        value_name = "FILE",
+        help = "Compose file; repeat for overrides, in the order docker compose merges them [default: docker-compose.yml]"
+    )]
+    pub compose_files: Vec<String>,
+    #[arg(
+        long = "compose-profile",
+        global = true,
+        value_name = "PROFILE",
+        value_delimiter = ',',
+        help = "Compose profile to activate for every docker compose call; repeat for several"
+    )]
+    pub compose_profiles: Vec<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Compose project name passed to docker compose -p"
+    )]
+    pub compose_project: Option<String>,
     #[arg(
         short = 'e',
         long = "env-file",
+        global = true,
         default_value = ".env",
         help = "Path to .env file"
     )]
     pub env_file: String,
-    #[arg(long, help = "Use Docker Swarm mode")]
+    #[arg(long, global = true, help = "Use Docker Swarm mode")]
     pub swarm: bool,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "How to roll out a new version [default: rolling]"
+    )]
+    pub strategy: Option<Strategy>,
+    #[arg(
+        long = "config",
+        global = true,
+        value_name = "FILE",
+        help = "Project file with named projects [default: rolling-deployer.yaml if present]"
+    )]
+    pub config: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "all",
+        help = "Project from the project file to act on"
+    )]
+    pub project: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Act on every project in the project file, in order"
+    )]
+    pub all: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Seconds to wait for each recreated container to become healthy [default: 60]"
+    )]
+    pub health_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Seconds a container without a HEALTHCHECK must stay running [default: 10]"
+    )]
+    pub health_grace_period: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECS",
+        help = "Seconds to wait for another rollout's deploy lock on the clone path [default: 300]"
+    )]
+    pub lock_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        help = "Take over a stale deploy lock that is still held after --lock-timeout"
+    )]
+    pub break_lock: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Subdirectory of the config repo to check out and mount (e.g. envs/prod/dynamic)"
+    )]
+    pub config_subpath: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Refuse to deploy tags without a trusted GPG or SSH signature"
+    )]
+    pub verify_tags: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "GnuPG home directory with the keys trusted to sign tags"
+    )]
+    pub gpg_home: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "SSH allowed-signers file trusted to sign tags"
+    )]
+    pub allowed_signers: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "SSH private key for cloning the config repo"
+    )]
+    pub ssh_key: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "known_hosts file the git server's SSH host key must be in"
+    )]
+    pub known_hosts: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "File holding an HTTPS token for the config repo [default: $GIT_TOKEN]"
+    )]
+    pub git_token_file: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "USER",
+        help = "Username sent with the HTTPS token [default: x-access-token]"
+    )]
+    pub git_token_user: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "HELPER",
+        help = "Git credential helper for the config repo (e.g. 'store --file /etc/git-credentials')"
+    )]
+    pub git_credential_helper: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Fail instead of prompting for git credentials or SSH host keys"
+    )]
+    pub non_interactive: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "ADDR",
+        help = "Traefik API address (e.g. http://127.0.0.1:8080) to confirm the new routers are loaded after each recreate"
+    )]
+    pub traefik_api: Option<String>,
+    #[arg(
+        long = "service",
+        global = true,
+        value_name = "NAME",
+        value_delimiter = ',',
+        help = "Compose service to rewrite and recreate; repeat for several [default: the first service mounting --mount-path]"
+    )]
+    pub services: Vec<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the plan for deploy/rollback without changing anything"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value = "text",
+        help = "Print a final machine-readable summary (json) on stdout"
+    )]
+    pub output: OutputFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
-// Main application logic
-pub async fn deploy(mut cli: CLI) {
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Deploy a version of the config repo: a tag, branch, commit SHA, semver requirement (`^1.4`) or `latest`
+    Deploy {
+        #[arg(value_name = "REF", required_unless_present = "artifact")]
+        tag: Option<String>,
+        /// Deploy from a local git bundle or .tar.gz (with manifest.json) instead of the repo
+        #[arg(long, value_name = "FILE")]
+        artifact: Option<PathBuf>,
+    },
+    /// Roll back to a previous version (defaults to the one deployed before `current`)
+    Rollback {
+        #[arg(value_name = "TAG")]
+        tag: Option<String>,
+    },
+    /// Show routers, services, middlewares and TLS options changed between two versions
+    /// (`diff <TO>` compares `current` with TO)
+    Diff {
+        #[arg(value_name = "REF", required = true, num_args = 1..=2)]
+        refs: Vec<String>,
+    },
+    /// Show which config version is live and what each container has mounted
+    Status,
+    /// Show the deployment history recorded under the clone path
+    History {
+        #[arg(long, value_enum, default_value = "table")]
+        format: HistoryFormat,
+        #[arg(long, help = "Only show the most recent N entries")]
+        limit: Option<usize>,
+    },
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Deploy { .. } => "deploy",
+            Command::Rollback { .. } => "rollback",
+            Command::Diff { .. } => "diff",
+            Command::Status => "status",
+            Command::History { .. } => "history",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum HistoryFormat {
+    Table,
+    Json,
+}
+
+/// Final result of a command, printed as a single JSON line with `--output json`.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub command: String,
+    /// `success`, `planned` (dry run), `failed` or `rolled_back`
+    pub status: String,
+    pub exit_code: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolled_back_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<DeployPlan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<StatusReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<HistoryEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<RouteDiff>,
+}
+
+impl Summary {
+    fn new(command: &Command) -> Self {
+        let tag = match command {
+            Command::Deploy { tag, .. } => tag.clone(),
+            Command::Rollback { tag } => tag.clone(),
+            Command::Diff { refs } => refs.last().cloned(),
+            _ => None,
+        };
+        Self {
+            command: command.name().to_string(),
+            status: "success".to_string(),
+            tag,
+            ..Default::default()
+        }
+    }
+
+    fn record_deploy(&mut self, entry: HistoryEntry) {
+        self.tag = Some(entry.tag);
+        self.commit = entry.commit;
+        self.containers = entry.containers;
+    }
+
+    fn record_plan(&mut self, plan: DeployPlan, output: OutputFormat) {
+        if output == OutputFormat::Text {
+            print!("{}", plan);
+        }
+        self.status = "planned".to_string();
+        self.tag = Some(plan.tag.clone());
+        self.plan = Some(plan);
+    }
+
+    fn fail(&mut self, error: &Error) {
+        self.exit_code = error.exit_code();
+        self.error = Some(error.to_string());
+        if let Error::RolledBack { tag, .. } = error {
+            self.status = "rolled_back".to_string();
+            self.rolled_back_to = Some(tag.clone());
+        } else {
+            self.status = "failed".to_string();
+        }
+    }
+}
+
+/// Runs the parsed command, prints its result and returns any error so the
+/// caller can turn it into an exit code (see `Error::exit_code`).
+///
+/// With a project file the command runs once per selected project, in file
+/// order, stopping at the first failure. Each project gets its own summary.
+pub async fn execute(cli: CLI) -> Result<()> {
     // Allow tests to skip real deployment logic
     if std::env::var("SKIP_DEPLOY").ok().as_deref() == Some("1") {
         tracing::info!("Skipping real deployment for test");
-        return;
+        return Ok(());
+    }
+
+    let projects = match select_projects(&cli) {
+        Ok(projects) => projects,
+        Err(e) => {
+            let mut summary = Summary::new(&cli.command);
+            report(&mut summary, Err(&e), cli.output);
+            return Err(e);
+        }
+    };
+
+    for settings in projects {
+        let mut summary = Summary::new(&cli.command);
+        let result = run_command(cli.clone(), settings.as_ref(), &mut summary).await;
+        report(&mut summary, result.as_ref().map(|_| ()), cli.output);
+        result?;
+    }
+    Ok(())
+}
+
+/// Prints the outcome of one command run.
+fn report(summary: &mut Summary, result: std::result::Result<(), &Error>, output: OutputFormat) {
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        summary.fail(e);
+    }
+    if output == OutputFormat::Json {
+        match serde_json::to_string(&summary) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to serialize summary: {}", e),
+        }
+    }
+}
+
+/// Resolves which project-file entries to run. `[None]` means no project file is
+/// in use and configuration comes from flags and `.env` only.
+fn select_projects(cli: &CLI) -> Result<Vec<Option<ProjectSettings>>> {
+    match ProjectFile::locate(cli.config.as_deref())? {
+        Some(path) => {
+            info!("Using project file {}", path.display());
+            let file = ProjectFile::load(&path)?;
+            let selected = file.select(cli.project.as_deref(), cli.all)?;
+            // One path for every project would undo the file's per-project clone_path
+            if selected.len() > 1 {
+                let shared = [
+                    ("--clone-path", &cli.clone_path),
+                    ("--mount-path", &cli.mount_path),
+                ];
+                if let Some((flag, _)) = shared.iter().find(|(_, value)| value.is_some()) {
+                    return Err(Error::Config(format!(
+                        "{} can't be combined with {} selected projects; set it per project in {}",
+                        flag,
+                        selected.len(),
+                        path.display()
+                    )));
+                }
+            }
+            Ok(selected.into_iter().map(Some).collect())
+        }
+        None if cli.project.is_some() || cli.all => Err(Error::Config(format!(
+            "--project and --all need a project file ({} or --config <path>)",
+            DEFAULT_PROJECT_FILES.join(", ")
+        ))),
+        None => Ok(vec![None]),
+    }
+}
+
+/// Fills CLI fields the user did not set from a project-file entry, so that
+/// flags win over the file and the file wins over `.env`.
+fn apply_project_settings(cli: &mut CLI, settings: &ProjectSettings) {
+    fn fill(field: &mut Option<String>, value: &Option<String>) {
+        if field.is_none() {
+            field.clone_from(value);
+        }
+    }
+    fn fill_default(field: &mut String, default: &str, value: &Option<String>) {
+        if let (true, Some(value)) = (field == default, value) {
+            *field = value.clone();
+        }
+    }
+
+    fill(&mut cli.name, &settings.name);
+    if cli.repo_url.as_deref() == Some(DEFAULT_REPO_URL) && settings.repo_url.is_some() {
+        cli.repo_url = None;
+    }
+    fill(&mut cli.repo_url, &settings.repo_url);
+    fill(&mut cli.clone_path, &settings.clone_path);
+    fill(&mut cli.mount_path, &settings.mount_path);
+    fill(&mut cli.config_subpath, &settings.config_subpath);
+    fill(&mut cli.gpg_home, &settings.gpg_home);
+    fill(&mut cli.allowed_signers, &settings.allowed_signers);
+    fill(&mut cli.ssh_key, &settings.ssh_key);
+    fill(&mut cli.known_hosts, &settings.known_hosts);
+    fill(&mut cli.git_token_file, &settings.git_token_file);
+    fill(&mut cli.git_token_user, &settings.git_token_user);
+    fill(
+        &mut cli.git_credential_helper,
+        &settings.git_credential_helper,
+    );
+    fill(&mut cli.traefik_api, &settings.traefik_api);
+    fill(&mut cli.image, &settings.image);
+    fill(&mut cli.compose_project, &settings.compose_project);
+    if cli.compose_files.is_empty() {
+        if let Some(files) = &settings.compose_file {
+            cli.compose_files = files.clone();
+        }
+    }
+    fill_default(
+        &mut cli.socket_path,
+        "/var/run/docker.sock",
+        &settings.socket_path,
+    );
+    cli.health_timeout = cli.health_timeout.or(settings.health_timeout);
+    cli.lock_timeout = cli.lock_timeout.or(settings.lock_timeout);
+    cli.health_grace_period = cli.health_grace_period.or(settings.health_grace_period);
+}
+
+fn missing_deploy_ref() -> Error {
+    Error::Config("deploy needs a REF or --artifact <FILE>".to_string())
+}
+
+async fn run_command(
+    mut cli: CLI,
+    settings: Option<&ProjectSettings>,
+    summary: &mut Summary,
+) -> Result<()> {
+    if let Some(settings) = settings {
+        apply_project_settings(&mut cli, settings);
     }
 
     // Load .env file if present and fill missing CLI fields
@@ -89,14 +548,6 @@ pub async fn deploy(mut cli: CLI) {
     } else {
         cli.socket_path.clone()
     };
-    let compose_file = if cli.compose_file == "docker-compose.yml" {
-        env_content
-            .get("COMPOSE_FILE")
-            .cloned()
-            .unwrap_or_else(|| cli.compose_file.clone())
-    } else {
-        cli.compose_file.clone()
-    };
 
     // Update CLI struct for downstream config loading
     cli.name = if name.is_empty() { None } else { Some(name) };
@@ -116,19 +567,21 @@ pub async fn deploy(mut cli: CLI) {
         Some(mount_path)
     };
     cli.socket_path = socket_path;
-    cli.compose_file = compose_file;
 
     // Ensure mount_path is set from CLI or .env
     if cli.mount_path.is_none() {
-        error!("MOUNT_PATH must be set via --mount-path or in the .env file");
-        eprintln!("MOUNT_PATH must be set via --mount-path or in the .env file");
-        return;
+        return Err(Error::Config(
+            "MOUNT_PATH must be set via --mount-path or in the .env file".to_string(),
+        ));
     }
     tracing::debug!("Mount path from CLI: {:?}", cli.mount_path);
 
-    // Load configuration from CLI args and/or .env file
+    // Load configuration from CLI args, the project file and/or .env file
     let config = match Config::from_env_and_cli(&cli) {
-        Ok(config) => {
+        Ok(mut config) => {
+            if let Some(settings) = settings {
+                config.apply_project_settings(settings, &cli);
+            }
             // Set up logging based on config.verbose
             let filter = match cli.verbose {
                 0 => "warn",
@@ -136,34 +589,129 @@ pub async fn deploy(mut cli: CLI) {
                 2 => "debug",
                 _ => "trace",
             };
-            tracing_subscriber::fmt()
+            let _ = tracing_subscriber::fmt()
                 .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
-                .init();
+                .try_init();
             info!("Configuration loaded:");
-            info!("  Repository: {}", config.repo_url);
+            info!("  Repository: {}", redact_url(&config.repo_url));
             info!("  Clone path: {}", config.clone_path);
             info!("  Mount path: {}", config.mount_path);
             config
         }
         Err(e) => {
-            error!("Configuration error: {}", e);
-            info!("");
-            Config::show_configuration_help();
-            return;
+            if cli.output == OutputFormat::Text {
+                Config::show_configuration_help();
+            }
+            return Err(e);
         }
     };
+    summary.project = Some(config.name.clone());
 
     let deployment_manager = DeploymentManager::new(config.clone());
+    // Deploys and rollbacks repair `current` under the deploy lock; nothing else touches it
+    let repairs = !cli.dry_run
+        && matches!(
+            cli.command,
+            Command::Deploy { .. } | Command::Rollback { .. }
+        );
+    if !repairs
+        && Path::new(&config.clone_path).exists()
+        && GitClient::current_is_broken(&config.clone_path)
+    {
+        eprintln!(
+            "Warning: '{}/current' is missing or dangling; the next deploy or rollback repairs it",
+            config.clone_path
+        );
+    }
 
-    info!(
-        "Starting deployment for project '{}' with tag '{}'",
-        config.name, cli.tag
-    );
+    match &cli.command {
+        Command::Deploy { tag, artifact } if cli.dry_run => {
+            let plan = match (artifact, tag) {
+                (Some(artifact), tag) => {
+                    let artifact = Artifact::open(artifact)?;
+                    deployment_manager
+                        .plan_artifact(&artifact, tag.as_deref())
+                        .await?
+                }
+                (None, Some(tag)) => deployment_manager.plan(tag).await?,
+                (None, None) => return Err(missing_deploy_ref()),
+            };
+            summary.record_plan(plan, cli.output);
+        }
+        Command::Rollback { tag } if cli.dry_run => {
+            let tag = deployment_manager.rollback_target(tag.as_deref())?;
+            let plan = deployment_manager.plan(&tag).await?;
+            summary.record_plan(plan, cli.output);
+        }
+        Command::Deploy { tag, artifact } => {
+            info!("Starting deployment for project '{}'", config.name);
 
-    match deployment_manager.rolling_deploy(&cli.tag, cli.swarm).await {
-        Ok(()) => info!("Rolling deployment successful!"),
-        Err(e) => error!("Rolling deployment failed: {}", e),
+            let entry = match (artifact, tag) {
+                (Some(artifact), tag) => {
+                    let artifact = Artifact::open(artifact)?;
+                    deployment_manager
+                        .deploy_artifact(&artifact, tag.as_deref())
+                        .await?
+                }
+                (None, Some(tag)) => deployment_manager.rolling_deploy(tag).await?,
+                (None, None) => return Err(missing_deploy_ref()),
+            };
+            info!("Rolling deployment successful!");
+            summary.record_deploy(entry);
+        }
+        Command::Rollback { tag } => {
+            info!("Starting rollback for project '{}'", config.name);
+
+            let entry = deployment_manager.rollback(tag.as_deref()).await?;
+            info!("Rollback successful!");
+            summary.record_deploy(entry);
+        }
+        Command::Diff { refs } => {
+            let (from, to) = match refs.as_slice() {
+                [to] => (None, to),
+                [from, to] => (Some(from.as_str()), to),
+                _ => return Err(Error::Config("diff takes one or two REFs".to_string())),
+            };
+            let diff = deployment_manager.diff(from, to).await?;
+            if cli.output == OutputFormat::Text {
+                print!("{}", diff);
+            }
+            summary.diff = Some(diff);
+        }
+        Command::Status => {
+            let report = deployment_manager.status().await?;
+            if cli.output == OutputFormat::Text {
+                print!("{}", report);
+                for container in report.stale_containers() {
+                    eprintln!(
+                        "Container '{}' is not running the current config (mounted: {})",
+                        container.name,
+                        container.mount_source.as_deref().unwrap_or("<not mounted>")
+                    );
+                }
+            }
+            summary.report = Some(report);
+        }
+        Command::History { format, limit } => {
+            let mut entries = deployment_manager.history().entries()?;
+            if let Some(limit) = limit {
+                let skip = entries.len().saturating_sub(*limit);
+                entries.drain(..skip);
+            }
+            if cli.output == OutputFormat::Json {
+                summary.history = Some(entries);
+                return Ok(());
+            }
+            match format {
+                HistoryFormat::Table => print!("{}", history::format_table(&entries)),
+                HistoryFormat::Json => match serde_json::to_string_pretty(&entries) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => error!("Failed to serialize deployment history: {}", e),
+                },
+            }
+        }
     }
+    Ok(())
 }
 
 // Extracts a value from the CLI or .env file, preferring the CLI value if present
@@ -203,23 +751,220 @@ mod tests {
     #[test]
     fn test_deploy_missing_name() {
         setup();
-        let cli = CLI {
-            tag: "v1.0.0".to_string(),
-            name: None,
-            socket_path: "/tmp/docker.sock".to_string(),
-            repo_url: Some("https://example.com/repo.git".to_string()),
-            clone_path: Some("/tmp/mount".to_string()),
-            mount_path: None,
-            verbose: 0,
-            compose_file: "docker-compose.yml".to_string(),
-            env_file: ".env".to_string(),
-            swarm: false,
-        };
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "deploy",
+            "v1.0.0",
+            "--socket-path",
+            "/tmp/docker.sock",
+            "--repo-url",
+            "https://example.com/repo.git",
+            "--clone-path",
+            "/tmp/mount",
+        ]);
         let rt = Runtime::new().unwrap();
-        rt.block_on(async {
-            deploy(cli).await;
+        let result = rt.block_on(async { execute(cli).await });
+        // No MOUNT_PATH anywhere: a configuration error, which exits with code 2
+        let err = result.unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_parse_rollback_without_tag() {
+        let cli = CLI::parse_from(["rolling-deployer", "rollback", "--swarm"]);
+        assert_eq!(cli.command, Command::Rollback { tag: None });
+        assert!(cli.swarm);
+    }
+
+    #[test]
+    fn test_parse_deploy_with_global_flags_after_tag() {
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "deploy",
+            "v1.2.3",
+            "--name",
+            "proxy",
+            "--service",
+            "traefik",
+            "--service",
+            "edge-a,edge-b",
+        ]);
+        assert_eq!(
+            cli.command,
+            Command::Deploy {
+                tag: Some("v1.2.3".to_string()),
+                artifact: None,
+            }
+        );
+        assert_eq!(cli.name.as_deref(), Some("proxy"));
+        assert_eq!(cli.services, ["traefik", "edge-a", "edge-b"]);
+    }
+
+    #[test]
+    fn test_parse_deploy_from_artifact() {
+        let cli = CLI::parse_from(["rolling-deployer", "deploy", "--artifact", "config.bundle"]);
+        assert_eq!(
+            cli.command,
+            Command::Deploy {
+                tag: None,
+                artifact: Some(PathBuf::from("config.bundle")),
+            }
+        );
+        assert!(CLI::try_parse_from(["rolling-deployer", "deploy"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_diff_refs() {
+        let cli = CLI::parse_from(["rolling-deployer", "diff", "v1.2.0"]);
+        assert_eq!(
+            cli.command,
+            Command::Diff {
+                refs: vec!["v1.2.0".to_string()]
+            }
+        );
+        let cli = CLI::parse_from(["rolling-deployer", "diff", "v1.1.0", "v1.2.0"]);
+        assert_eq!(Summary::new(&cli.command).tag.as_deref(), Some("v1.2.0"));
+        assert!(CLI::try_parse_from(["rolling-deployer", "diff"]).is_err());
+        assert!(CLI::try_parse_from(["rolling-deployer", "diff", "a", "b", "c"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_history_options() {
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "history",
+            "--format",
+            "json",
+            "--limit",
+            "5",
+        ]);
+        assert_eq!(
+            cli.command,
+            Command::History {
+                format: HistoryFormat::Json,
+                limit: Some(5)
+            }
+        );
+    }
+
+    #[test]
+    fn test_project_settings_fill_only_unset_flags() {
+        let mut cli = CLI::parse_from([
+            "rolling-deployer",
+            "deploy",
+            "v1.0.0",
+            "--clone-path",
+            "/from/cli",
+        ]);
+        let settings = ProjectSettings {
+            name: Some("edge".to_string()),
+            repo_url: Some("https://example.com/edge.git".to_string()),
+            clone_path: Some("/from/file".to_string()),
+            compose_file: Some(vec![
+                "/srv/edge/docker-compose.yml".to_string(),
+                "/srv/edge/docker-compose.prod.yml".to_string(),
+            ]),
+            health_timeout: Some(120),
+            ..Default::default()
+        };
+        apply_project_settings(&mut cli, &settings);
+        assert_eq!(cli.name.as_deref(), Some("edge"));
+        assert_eq!(
+            cli.repo_url.as_deref(),
+            Some("https://example.com/edge.git")
+        );
+        assert_eq!(cli.clone_path.as_deref(), Some("/from/cli"));
+        assert_eq!(
+            cli.compose_files,
+            [
+                "/srv/edge/docker-compose.yml",
+                "/srv/edge/docker-compose.prod.yml"
+            ]
+        );
+        assert_eq!(cli.socket_path, "/var/run/docker.sock");
+        assert_eq!(cli.health_timeout, Some(120));
+    }
+
+    #[test]
+    fn test_all_projects_reject_shared_clone_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("rolling-deployer.yaml");
+        std::fs::write(
+            &file,
+            "projects:\n  - name: edge\n    clone_path: /opt/edge\n  - name: internal\n    clone_path: /opt/internal\n",
+        )
+        .unwrap();
+        let file = file.to_str().unwrap();
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "status",
+            "--config",
+            file,
+            "--all",
+            "--clone-path",
+            "/opt/shared",
+        ]);
+        let err = select_projects(&cli).unwrap_err();
+        assert!(err.to_string().contains("--clone-path"), "{}", err);
+
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "status",
+            "--config",
+            file,
+            "--project",
+            "edge",
+            "--clone-path",
+            "/opt/shared",
+        ]);
+        assert_eq!(select_projects(&cli).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_image_defaults_to_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join(".env");
+        std::fs::write(
+            &env_file,
+            "NAME=edge-proxy\nREPO_URL=https://example.com/edge.git\nCLONE_PATH=/opt/configs\nMOUNT_PATH=/etc/traefik/dynamic\n",
+        )
+        .unwrap();
+        let env_file = env_file.to_str().unwrap();
+        let cli = CLI::parse_from(["rolling-deployer", "status", "--env-file", env_file]);
+        assert_eq!(Config::from_env_and_cli(&cli).unwrap().image, "edge-proxy");
+
+        let cli = CLI::parse_from([
+            "rolling-deployer",
+            "status",
+            "--env-file",
+            env_file,
+            "--image",
+            "traefik",
+        ]);
+        assert_eq!(Config::from_env_and_cli(&cli).unwrap().image, "traefik");
+    }
+
+    #[test]
+    fn test_summary_for_rolled_back_deploy() {
+        let mut summary = Summary::new(&Command::Deploy {
+            tag: Some("v2.0.0".to_string()),
+            artifact: None,
+        });
+        summary.fail(&Error::RolledBack {
+            tag: "v1.0.0".to_string(),
+            cause: Box::new(Error::Health {
+                service: "traefik".to_string(),
+                reason: "container reported unhealthy".to_string(),
+            }),
         });
-        // This test just ensures no panic and covers the error path for missing name
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&summary).unwrap()).unwrap();
+        assert_eq!(json["command"], "deploy");
+        assert_eq!(json["status"], "rolled_back");
+        assert_eq!(json["tag"], "v2.0.0");
+        assert_eq!(json["rolled_back_to"], "v1.0.0");
+        assert_eq!(json["exit_code"], 6);
     }
 
     #[test]
@@ -0,0 +1,411 @@
+use std::ops::Range;
+
+/// A change to one entry of a service's `volumes` list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeEdit<'a> {
+    /// Replace a short-syntax entry (`host:container[:mode]`) as a whole.
+    Entry(&'a str),
+    /// Set the `source:` key of a long-syntax entry.
+    Source(&'a str),
+}
+
+/// One line of the file, without its line ending.
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Style {
+    Plain,
+    Single,
+    Double,
+}
+
+/// Applies `edit` to entry `index` of `service`'s volumes, changing nothing but that
+/// scalar (or adding a `source:` line). Comments, anchors, quoting and layout elsewhere
+/// stay byte-for-byte identical. Returns `None` if the entry can't be located in
+/// block-style YAML, e.g. in a flow sequence or behind an alias.
+pub fn set_volume(content: &str, service: &str, index: usize, edit: VolumeEdit) -> Option<String> {
+    let lines = lines(content);
+    let items = sequence_items(&lines, volumes_block(&lines, service)?)?;
+    let item = items.get(index)?.clone();
+    let first = lines[item.start];
+    let dash = indent(first.text);
+    let after_dash = dash + 1 + indent(&first.text[dash + 1..]);
+
+    match edit {
+        VolumeEdit::Entry(entry) => {
+            let (span, style) = scalar_span(first.text, after_dash)?;
+            let span = first.start + span.start..first.start + span.end;
+            Some(splice(content, span, &format_scalar(entry, style)))
+        }
+        VolumeEdit::Source(source) => {
+            // The mapping starts either on the dash line or on the next content line
+            let key_indent = if is_content(&first.text[after_dash..]) {
+                after_dash
+            } else {
+                item.clone()
+                    .skip(1)
+                    .map(|i| lines[i].text)
+                    .find(|text| is_content(text))
+                    .map(indent)?
+            };
+            let keys: Vec<usize> = item
+                .clone()
+                .filter(|&i| {
+                    let text = lines[i].text;
+                    if i == item.start {
+                        key_indent == after_dash
+                    } else {
+                        is_content(text) && indent(text) == key_indent
+                    }
+                })
+                .collect();
+            for &i in &keys {
+                let line = lines[i];
+                let (key, value_at) = parse_key(&line.text[key_indent..])?;
+                if key != "source" {
+                    continue;
+                }
+                let from = key_indent + value_at;
+                let from = from + indent(&line.text[from..]);
+                let (span, style) = scalar_span(line.text, from)?;
+                let span = line.start + span.start..line.start + span.end;
+                return Some(splice(content, span, &format_scalar(source, style)));
+            }
+            // No `source:` yet: add one after the entry's last key
+            let last = lines[*keys.last()?];
+            let new_line = format!(
+                "{}source: {}",
+                " ".repeat(key_indent),
+                format_scalar(source, Style::Plain)
+            );
+            let at = last.start + last.text.len();
+            Some(splice(
+                content,
+                at..at,
+                &format!("{}{}", eol(content), new_line),
+            ))
+        }
+    }
+}
+
+/// Appends a short-syntax `entry` to `service`'s volumes list, indented like the
+/// existing entries. Returns `None` if the list can't be located.
+pub fn append_volume(content: &str, service: &str, entry: &str) -> Option<String> {
+    let lines = lines(content);
+    let items = sequence_items(&lines, volumes_block(&lines, service)?)?;
+    let first = lines[items.first()?.start];
+    let last = lines[items.last()?.end - 1];
+    let new_line = format!(
+        "{}- {}",
+        " ".repeat(indent(first.text)),
+        format_scalar(entry, Style::Plain)
+    );
+    let at = last.start + last.text.len();
+    Some(splice(
+        content,
+        at..at,
+        &format!("{}{}", eol(content), new_line),
+    ))
+}
+
+fn lines(content: &str) -> Vec<Line<'_>> {
+    let mut start = 0;
+    content
+        .split_inclusive('\n')
+        .map(|raw| {
+            let line = Line {
+                start,
+                text: raw.trim_end_matches('\n').trim_end_matches('\r'),
+            };
+            start += raw.len();
+            line
+        })
+        .collect()
+}
+
+fn eol(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn splice(content: &str, span: Range<usize>, with: &str) -> String {
+    format!("{}{}{}", &content[..span.start], with, &content[span.end..])
+}
+
+fn indent(text: &str) -> usize {
+    text.len() - text.trim_start_matches(' ').len()
+}
+
+/// Whether a line holds anything besides whitespace, a comment or a document marker.
+fn is_content(text: &str) -> bool {
+    let trimmed = text.trim();
+    !(trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---")
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    trimmed == "-" || trimmed.starts_with("- ")
+}
+
+/// The lines of the `services.<service>.volumes` sequence.
+fn volumes_block(lines: &[Line], service: &str) -> Option<Range<usize>> {
+    let services = find_key(lines, 0..lines.len(), "services")?;
+    let service = find_key(lines, block(lines, services), service)?;
+    let volumes = find_key(lines, block(lines, service), "volumes")?;
+    Some(block(lines, volumes))
+}
+
+/// The lines nested under the key on line `at`. A sequence may sit at the key's own
+/// indentation. Trailing comments and blank lines are left out.
+fn block(lines: &[Line], at: usize) -> Range<usize> {
+    let key_indent = indent(lines[at].text);
+    let mut end = at + 1;
+    for (i, line) in lines.iter().enumerate().skip(at + 1) {
+        if !is_content(line.text) {
+            continue;
+        }
+        let depth = indent(line.text);
+        if depth > key_indent || (depth == key_indent && is_sequence_item(line.text)) {
+            end = i + 1;
+        } else {
+            break;
+        }
+    }
+    at + 1..end
+}
+
+/// Finds the line of mapping key `key` among the direct children in `range`. Keys
+/// whose value is written inline (flow collections, aliases) are not matched.
+fn find_key(lines: &[Line], range: Range<usize>, key: &str) -> Option<usize> {
+    let child_indent = range
+        .clone()
+        .map(|i| lines[i].text)
+        .find(|text| is_content(text))
+        .map(indent)?;
+    range.into_iter().find(|&i| {
+        let text = lines[i].text;
+        is_content(text)
+            && indent(text) == child_indent
+            && parse_key(&text[child_indent..]).is_some_and(|(name, value_at)| {
+                name == key && !is_content(&text[child_indent + value_at..])
+            })
+    })
+}
+
+/// The line ranges of each item of the block sequence in `range`.
+fn sequence_items(lines: &[Line], range: Range<usize>) -> Option<Vec<Range<usize>>> {
+    let item_indent = range
+        .clone()
+        .map(|i| lines[i].text)
+        .find(|text| is_content(text))
+        .map(indent)?;
+    let mut items: Vec<Range<usize>> = Vec::new();
+    for i in range {
+        let text = lines[i].text;
+        if !is_content(text) {
+            continue;
+        }
+        if indent(text) == item_indent {
+            if !is_sequence_item(text) {
+                return None;
+            }
+            items.push(i..i + 1);
+        } else if let Some(item) = items.last_mut() {
+            item.end = i + 1;
+        }
+    }
+    Some(items)
+}
+
+/// Splits `key: value` at the start of `text`, returning the key and the offset just
+/// past its colon.
+fn parse_key(text: &str) -> Option<(String, usize)> {
+    let (key, colon) = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let (span, _) = scalar_span(text, 0)?;
+            let inner = &text[1..span.end - 1];
+            let key = if quote == '"' {
+                inner.replace("\\\"", "\"")
+            } else {
+                inner.replace("''", "'")
+            };
+            (key, span.end)
+        }
+        '-' | '[' | '{' | '?' | '#' => return None,
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(at, _)| at)
+                .find(|&at| matches!(text[at + 1..].chars().next(), None | Some(' ')))?;
+            (text[..colon].trim_end().to_string(), colon)
+        }
+    };
+    text[colon..].starts_with(':').then_some((key, colon + 1))
+}
+
+/// The span of the scalar starting at `from`, including its quotes.
+fn scalar_span(text: &str, from: usize) -> Option<(Range<usize>, Style)> {
+    let rest = &text[from..];
+    match rest.chars().next()? {
+        '"' => {
+            let mut escaped = false;
+            let close = rest.char_indices().skip(1).find(|&(_, c)| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            })?;
+            Some((from..from + close.0 + 1, Style::Double))
+        }
+        '\'' => {
+            let bytes = rest.as_bytes();
+            let mut at = 1;
+            while at < bytes.len() {
+                if bytes[at] == b'\'' {
+                    if bytes.get(at + 1) == Some(&b'\'') {
+                        at += 2;
+                        continue;
+                    }
+                    return Some((from..from + at + 1, Style::Single));
+                }
+                at += 1;
+            }
+            None
+        }
+        '[' | '{' | '&' | '*' | '!' | '|' | '>' | '#' => None,
+        _ => {
+            let end = rest.find(" #").unwrap_or(rest.len());
+            Some((from..from + rest[..end].trim_end().len(), Style::Plain))
+        }
+    }
+}
+
+/// Writes `value` in `style`, quoting a plain scalar that YAML would read differently.
+fn format_scalar(value: &str, style: Style) -> String {
+    let needs_quotes = value.is_empty()
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.ends_with(':')
+        || value.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || value.ends_with(char::is_whitespace);
+    match style {
+        Style::Single => format!("'{}'", value.replace('\'', "''")),
+        Style::Plain if !needs_quotes => value.to_string(),
+        _ => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"# Edge proxy, maintained by hand
+x-logging: &logging
+  driver: json-file   # keep small
+  options: {max-size: "10m"}
+
+services:
+  db:
+    image: postgres:16
+    volumes:
+    - data:/var/lib/postgresql/data
+
+  "traefik":
+    image: traefik:v3.1
+    logging: *logging
+    volumes:
+      # dynamic config, switched by rolling-deployer
+      - "/opt/configs/traefik-config-v1:/etc/traefik/dynamic:ro"   # pinned
+      - type: bind
+        source: '/opt/configs/traefik-config-v1/certs'
+        target: /certs
+      -
+        type: bind
+        target: /plugins
+volumes:
+  data: {}
+"#;
+
+    #[test]
+    fn test_set_volume_changes_only_the_scalar() {
+        let updated = set_volume(
+            COMPOSE,
+            "traefik",
+            0,
+            VolumeEdit::Entry("/opt/configs/current:/etc/traefik/dynamic:ro"),
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            COMPOSE.replace(
+                "\"/opt/configs/traefik-config-v1:/etc/traefik/dynamic:ro\"",
+                "\"/opt/configs/current:/etc/traefik/dynamic:ro\""
+            )
+        );
+
+        let updated = set_volume(COMPOSE, "traefik", 1, VolumeEdit::Source("/it's")).unwrap();
+        assert_eq!(
+            updated,
+            COMPOSE.replace("'/opt/configs/traefik-config-v1/certs'", "'/it''s'")
+        );
+
+        let updated =
+            set_volume(COMPOSE, "traefik", 2, VolumeEdit::Source("/opt/plugins")).unwrap();
+        assert_eq!(
+            updated,
+            COMPOSE.replace(
+                "        target: /plugins\n",
+                "        target: /plugins\n        source: /opt/plugins\n"
+            )
+        );
+
+        let updated = set_volume(
+            COMPOSE,
+            "db",
+            0,
+            VolumeEdit::Entry("pg:/var/lib/postgresql/data"),
+        )
+        .unwrap();
+        assert_eq!(updated, COMPOSE.replace("- data:/var", "- pg:/var"));
+        assert_eq!(
+            set_volume(COMPOSE, "db", 1, VolumeEdit::Entry("x:/y")),
+            None
+        );
+        assert_eq!(
+            set_volume(COMPOSE, "proxy", 0, VolumeEdit::Entry("x:/y")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_append_volume_keeps_indentation_and_line_endings() {
+        let updated = append_volume(
+            COMPOSE,
+            "db",
+            "/opt/configs/current:/etc/traefik/dynamic:rw",
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            COMPOSE.replace(
+                "    - data:/var/lib/postgresql/data\n",
+                "    - data:/var/lib/postgresql/data\n    - /opt/configs/current:/etc/traefik/dynamic:rw\n"
+            )
+        );
+
+        let crlf = "services:\r\n  traefik:\r\n    volumes:\r\n      - a:/b\r\n";
+        assert_eq!(
+            append_volume(crlf, "traefik", "c:/d").unwrap(),
+            "services:\r\n  traefik:\r\n    volumes:\r\n      - a:/b\r\n      - c:/d\r\n"
+        );
+
+        let flow = "services:\n  traefik:\n    volumes: [\"a:/b\"]\n";
+        assert_eq!(append_volume(flow, "traefik", "c:/d"), None);
+    }
+}
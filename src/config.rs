@@ -1,18 +1,75 @@
 use crate::cli::CLI;
+use crate::error::{Error, Result};
+use crate::git_client::GitAuth;
+use crate::project_file::ProjectSettings;
+use crate::traefik_api;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// How a new config version is rolled out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Recreate each compose service in turn (`docker compose up --force-recreate`).
+    #[default]
+    Rolling,
+    /// Swap the mount on a Docker Swarm service (`docker service update`).
+    Swarm,
+    /// Switch `current` under a Traefik that watches it, without recreating anything.
+    Reload,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub repo_url: String,
     pub clone_path: String,
-    pub compose_file: String,
+    /// Compose files in the order `docker compose -f` merges them; the first one's
+    /// directory is the compose project directory.
+    pub compose_files: Vec<String>,
+    /// Compose profiles activated for every `docker compose` call.
+    pub compose_profiles: Vec<String>,
+    /// Compose project name (`docker compose -p`), if not derived by compose itself.
+    pub compose_project: Option<String>,
     pub mount_path: String,
+    /// Project name, used to pick it from the project file and in reports.
     pub name: String,
+    /// Substring of the image of the Traefik containers to roll; `name` unless set.
+    pub image: String,
     pub socket_path: String,
+    /// Seconds to wait for a recreated container to become healthy.
+    pub health_timeout: u64,
+    /// Seconds a container without a HEALTHCHECK must stay running to count as healthy.
+    pub health_grace_period: u64,
+    /// Compose services whose volume is rewritten and which are rolled; empty means the
+    /// first service mounting `mount_path` and every running container of the project.
+    pub services: Vec<String>,
+    pub strategy: Strategy,
+    /// Seconds to wait for another rollout's deploy lock before giving up.
+    pub lock_timeout: u64,
+    /// Take over a deploy lock that is still held after `lock_timeout`.
+    pub break_lock: bool,
+    /// Subdirectory of the config repo that is mounted instead of the repo root.
+    pub config_subpath: Option<String>,
+    /// Refuse tags without a signature trusted by `gpg_home` / `allowed_signers`.
+    pub verify_tags: bool,
+    /// GnuPG home directory with the public keys trusted to sign tags.
+    pub gpg_home: Option<String>,
+    /// SSH allowed-signers file trusted to sign tags.
+    pub allowed_signers: Option<String>,
+    /// Credentials and prompt settings for talking to the config repo.
+    pub git_auth: GitAuth,
+    /// `host:port` of Traefik's API, polled after each recreate to confirm the new
+    /// routers are loaded.
+    pub traefik_api: Option<String>,
 }
 
+pub const DEFAULT_HEALTH_TIMEOUT: u64 = 60;
+pub const DEFAULT_HEALTH_GRACE_PERIOD: u64 = 10;
+pub const DEFAULT_LOCK_TIMEOUT: u64 = 300;
+pub const DEFAULT_COMPOSE_FILE: &str = "docker-compose.yml";
+
 impl Config {
-    pub fn from_env_and_cli(cli: &CLI) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_env_and_cli(cli: &CLI) -> Result<Self> {
         // Parse env file into a HashMap (don't crash if it doesn't exist)
         let mut env_vars = HashMap::new();
         let env_path = &cli.env_file;
@@ -36,39 +93,69 @@ impl Config {
             .repo_url
             .clone()
             .or_else(|| env_vars.get("REPO_URL").cloned())
-            .ok_or("REPO_URL not provided. Use --repo-url flag or set REPO_URL in .env file")?;
+            .ok_or_else(|| {
+                Error::Config(
+                    "REPO_URL not provided. Use --repo-url flag or set REPO_URL in .env file"
+                        .to_string(),
+                )
+            })?;
 
         let clone_path = cli
             .clone_path
             .clone()
             .or_else(|| env_vars.get("CLONE_PATH").cloned())
-            .ok_or(
-                "CLONE_PATH not provided. Use --clone-path flag or set CLONE_PATH in .env file",
-            )?;
+            .ok_or_else(|| {
+                Error::Config(
+                    "CLONE_PATH not provided. Use --clone-path flag or set CLONE_PATH in .env file"
+                        .to_string(),
+                )
+            })?;
 
         let mount_path = cli
             .mount_path
             .clone()
             .or_else(|| env_vars.get("MOUNT_PATH").cloned())
-            .ok_or(
-                "MOUNT_PATH not provided. Use --mount-path flag or set MOUNT_PATH in .env file",
-            )?;
+            .ok_or_else(|| {
+                Error::Config(
+                    "MOUNT_PATH not provided. Use --mount-path flag or set MOUNT_PATH in .env file"
+                        .to_string(),
+                )
+            })?;
 
-        // New: allow compose_file, name, and socket_path from .env
-        let compose_file = if cli.compose_file != "docker-compose.yml" {
-            cli.compose_file.clone()
+        // New: allow compose files, name, and socket_path from .env. COMPOSE_FILE and
+        // COMPOSE_PROFILES use the separators docker compose itself uses.
+        let mut compose_files = if cli.compose_files.is_empty() {
+            parse_list(&env_vars, "COMPOSE_FILE", ':')
         } else {
-            env_vars
-                .get("COMPOSE_FILE")
-                .cloned()
-                .unwrap_or_else(|| cli.compose_file.clone())
+            cli.compose_files.clone()
+        };
+        if compose_files.is_empty() {
+            compose_files.push(DEFAULT_COMPOSE_FILE.to_string());
+        }
+        let compose_profiles = if cli.compose_profiles.is_empty() {
+            parse_list(&env_vars, "COMPOSE_PROFILES", ',')
+        } else {
+            cli.compose_profiles.clone()
         };
+        let compose_project = cli
+            .compose_project
+            .clone()
+            .or_else(|| env_vars.get("COMPOSE_PROJECT_NAME").cloned());
 
         let name = cli
             .name
             .clone()
             .or_else(|| env_vars.get("NAME").cloned())
-            .ok_or("NAME not provided. Use --name flag or set NAME in .env file")?;
+            .ok_or_else(|| {
+                Error::Config(
+                    "NAME not provided. Use --name flag or set NAME in .env file".to_string(),
+                )
+            })?;
+        let image = cli
+            .image
+            .clone()
+            .or_else(|| env_vars.get("IMAGE").cloned())
+            .unwrap_or_else(|| name.clone());
         let socket_path = if cli.socket_path != "/var/run/docker.sock" {
             cli.socket_path.clone()
         } else {
@@ -78,31 +165,264 @@ impl Config {
                 .unwrap_or_else(|| cli.socket_path.clone())
         };
 
+        let health_timeout = match cli.health_timeout {
+            Some(secs) => secs,
+            None => parse_seconds(&env_vars, "HEALTH_TIMEOUT")?.unwrap_or(DEFAULT_HEALTH_TIMEOUT),
+        };
+        let health_grace_period = match cli.health_grace_period {
+            Some(secs) => secs,
+            None => parse_seconds(&env_vars, "HEALTH_GRACE_PERIOD")?
+                .unwrap_or(DEFAULT_HEALTH_GRACE_PERIOD),
+        };
+        let lock_timeout = match cli.lock_timeout {
+            Some(secs) => secs,
+            None => parse_seconds(&env_vars, "LOCK_TIMEOUT")?.unwrap_or(DEFAULT_LOCK_TIMEOUT),
+        };
+
+        let config_subpath = cli
+            .config_subpath
+            .clone()
+            .or_else(|| env_vars.get("CONFIG_SUBPATH").cloned())
+            .map(|subpath| validate_subpath(&subpath))
+            .transpose()?;
+        let verify_tags = cli.verify_tags || parse_bool(&env_vars, "VERIFY_TAGS")?.unwrap_or(false);
+        let gpg_home = cli
+            .gpg_home
+            .clone()
+            .or_else(|| env_vars.get("GPG_HOME").cloned());
+        let allowed_signers = cli
+            .allowed_signers
+            .clone()
+            .or_else(|| env_vars.get("ALLOWED_SIGNERS").cloned());
+
+        let traefik_api = cli
+            .traefik_api
+            .clone()
+            .or_else(|| env_vars.get("TRAEFIK_API").cloned())
+            .map(|address| traefik_api::parse_address(&address))
+            .transpose()?;
+
+        let services = if cli.services.is_empty() {
+            parse_list(&env_vars, "SERVICES", ',')
+        } else {
+            cli.services.clone()
+        };
+
+        let strategy = if cli.swarm {
+            Strategy::Swarm
+        } else if let Some(strategy) = cli.strategy {
+            strategy
+        } else if let Some(value) = env_vars.get("STRATEGY") {
+            <Strategy as clap::ValueEnum>::from_str(value, true).map_err(|_| {
+                Error::Config(format!(
+                    "STRATEGY must be rolling, swarm or reload, got '{}'",
+                    value
+                ))
+            })?
+        } else {
+            Strategy::Rolling
+        };
+
+        let git_auth = GitAuth {
+            ssh_key: cli
+                .ssh_key
+                .clone()
+                .or_else(|| env_vars.get("SSH_KEY").cloned()),
+            known_hosts: cli
+                .known_hosts
+                .clone()
+                .or_else(|| env_vars.get("KNOWN_HOSTS").cloned()),
+            token: read_git_token(
+                cli.git_token_file
+                    .as_ref()
+                    .or_else(|| env_vars.get("GIT_TOKEN_FILE")),
+            )?,
+            token_user: cli
+                .git_token_user
+                .clone()
+                .or_else(|| env_vars.get("GIT_TOKEN_USER").cloned()),
+            credential_helper: cli
+                .git_credential_helper
+                .clone()
+                .or_else(|| env_vars.get("GIT_CREDENTIAL_HELPER").cloned()),
+            non_interactive: cli.non_interactive
+                || parse_bool(&env_vars, "GIT_NON_INTERACTIVE")?.unwrap_or(false),
+        };
+
         Ok(Config {
             repo_url,
             clone_path,
-            compose_file,
+            compose_files,
+            compose_profiles,
+            compose_project,
             mount_path,
             name,
+            image,
             socket_path,
+            health_timeout,
+            health_grace_period,
+            services,
+            strategy,
+            lock_timeout,
+            break_lock: cli.break_lock,
+            config_subpath,
+            verify_tags,
+            gpg_home,
+            allowed_signers,
+            git_auth,
+            traefik_api,
         })
     }
 
+    /// Applies the project-file settings that weren't given on the command line.
+    pub fn apply_project_settings(&mut self, settings: &ProjectSettings, cli: &CLI) {
+        if cli.compose_profiles.is_empty() {
+            if let Some(profiles) = &settings.compose_profiles {
+                self.compose_profiles = profiles.clone();
+            }
+        }
+        if cli.services.is_empty() {
+            if let Some(services) = &settings.services {
+                self.services = services.clone();
+            }
+        }
+        if !cli.swarm && cli.strategy.is_none() {
+            if let Some(strategy) = settings.strategy {
+                self.strategy = strategy;
+            }
+        }
+        if !cli.verify_tags {
+            if let Some(verify_tags) = settings.verify_tags {
+                self.verify_tags = verify_tags;
+            }
+        }
+        if !cli.non_interactive {
+            if let Some(non_interactive) = settings.git_non_interactive {
+                self.git_auth.non_interactive = non_interactive;
+            }
+        }
+    }
+
     pub fn show_configuration_help() {
         println!("Configuration options:");
         println!("  1. Command line flags:");
         println!(
-            "     ./app v1.2.3 --name my-project --repo-url https://github.com/org/repo.git --mount-path /opt/configs --clone-path /opt/traefik-configs --compose-file ./docker-compose.yml --socket-path /var/run/docker.sock --env-file .env"
+            "     ./app deploy v1.2.3 --name my-project --repo-url https://github.com/org/repo.git --mount-path /opt/configs --clone-path /opt/traefik-configs --compose-file ./docker-compose.yml --socket-path /var/run/docker.sock --env-file .env"
         );
         println!();
         println!("  2. Create a .env file (or use --env-file to specify a different file):");
         println!("     REPO_URL=https://github.com/your-org/traefik-config.git");
         println!("     CLONE_PATH=/opt/traefik-configs");
         println!("     MOUNT_PATH=/etc/traefik/dynamic");
-        println!("     COMPOSE_FILE=./docker-compose.yml");
+        println!("     COMPOSE_FILE=./docker-compose.yml:./docker-compose.prod.yml");
+        println!("     COMPOSE_PROFILES=edge,metrics");
+        println!("     COMPOSE_PROJECT_NAME=proxy");
         println!("     NAME=my-project");
+        println!("     IMAGE=traefik   (image substring of the containers to roll, default: NAME)");
         println!("     SOCKET_PATH=/var/run/docker.sock");
+        println!("     HEALTH_TIMEOUT=60");
+        println!("     HEALTH_GRACE_PERIOD=10");
+        println!("     LOCK_TIMEOUT=300");
+        println!("     CONFIG_SUBPATH=envs/prod/dynamic");
+        println!("     SSH_KEY=/etc/rolling-deployer/deploy_key");
+        println!("     KNOWN_HOSTS=/etc/rolling-deployer/known_hosts");
+        println!("     GIT_TOKEN_FILE=/run/secrets/git-token   (or GIT_TOKEN in the environment)");
+        println!("     GIT_NON_INTERACTIVE=true");
+        println!("     VERIFY_TAGS=true");
+        println!("     ALLOWED_SIGNERS=/etc/rolling-deployer/allowed_signers");
+        println!("     TRAEFIK_API=http://127.0.0.1:8080");
+        println!("     STRATEGY=reload   (rolling, swarm or reload)");
+        println!("     SERVICES=traefik,traefik-internal");
         println!();
-        println!("Command line flags take precedence over .env file values.");
+        println!(
+            "  3. Create a rolling-deployer.yaml (or .toml, or use --config) with named projects:"
+        );
+        println!("     defaults:");
+        println!("       clone_path: /opt/traefik-configs");
+        println!("     projects:");
+        println!("       - name: my-project");
+        println!("         repo_url: https://github.com/your-org/traefik-config.git");
+        println!("         mount_path: /etc/traefik/dynamic");
+        println!("         services: [traefik]");
+        println!("     Select one with --project <name>, or deploy every project with --all.");
+        println!();
+        println!("Command line flags take precedence over the project file, which takes precedence over .env file values.");
     }
 }
+
+/// Reads the HTTPS token for the config repo from `token_file`, or else from the
+/// `GIT_TOKEN` environment variable. Tokens are deliberately not read from `.env`.
+fn read_git_token(token_file: Option<&String>) -> Result<Option<String>> {
+    let token = match token_file {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?,
+        None => match std::env::var("GIT_TOKEN") {
+            Ok(token) => token,
+            Err(_) => return Ok(None),
+        },
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(Error::Config(match token_file {
+            Some(path) => format!("Git token file {} is empty", path),
+            None => "GIT_TOKEN is set but empty".to_string(),
+        }));
+    }
+    Ok(Some(token.to_string()))
+}
+
+/// Normalizes a `config_subpath`: relative, inside the repo, without trailing slashes.
+fn validate_subpath(subpath: &str) -> Result<String> {
+    let trimmed = subpath.trim_matches('/');
+    let escapes = std::path::Path::new(trimmed)
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)));
+    if subpath.starts_with('/') || trimmed.is_empty() || escapes {
+        return Err(Error::Config(format!(
+            "CONFIG_SUBPATH must be a relative path inside the config repo, got '{}'",
+            subpath
+        )));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Splits a `sep`-separated list, dropping empty items.
+fn parse_list(env_vars: &HashMap<String, String>, key: &str, sep: char) -> Vec<String> {
+    env_vars
+        .get(key)
+        .map(|list| {
+            list.split(sep)
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_bool(env_vars: &HashMap<String, String>, key: &str) -> Result<Option<bool>> {
+    env_vars
+        .get(key)
+        .map(|value| match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" | "" => Ok(false),
+            _ => Err(Error::Config(format!(
+                "{} must be true or false, got '{}'",
+                key, value
+            ))),
+        })
+        .transpose()
+}
+
+fn parse_seconds(env_vars: &HashMap<String, String>, key: &str) -> Result<Option<u64>> {
+    env_vars
+        .get(key)
+        .map(|value| {
+            value.parse::<u64>().map_err(|e| {
+                Error::Config(format!(
+                    "{} must be a number of seconds, got '{}': {}",
+                    key, value, e
+                ))
+            })
+        })
+        .transpose()
+}
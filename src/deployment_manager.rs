@@ -1,10 +1,61 @@
-use crate::{config::Config, docker_client::DockerClient, git_client::GitClient};
+use crate::{
+    artifact::{Artifact, ArtifactKind},
+    compose_edit::{self, VolumeEdit},
+    config::{Config, Strategy},
+    docker_client::DockerClient,
+    error::{Error, Result},
+    git_client::{select_bundle_tag, DeployTarget, GitClient, SignaturePolicy, TargetKind},
+    history::{DeployMode, History, HistoryEntry, Outcome},
+    lock::DeployLock,
+    plan::{DeployPlan, PlannedRecreate, SymlinkChange},
+    route_diff::RouteDiff,
+    status::{ContainerStatus, StatusReport},
+    traefik::{self, DynamicConfig, ReloadLog},
+    traefik_api::{expected_routers, ExpectedRouter, TraefikApi},
+    types::{Container, Mount},
+};
 use serde_yaml::Value;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often container health is polled while waiting on a recreated service.
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of versioned config directories kept after a deploy.
+const KEEP_VERSIONS: usize = 3;
+
+/// A compose file rewritten by a deploy, with what to restore on rollback.
+#[derive(Debug)]
+struct ComposeUpdate {
+    file: String,
+    original: String,
+    updated: String,
+}
+
+/// Where a deploy takes its config version from.
+enum Source<'a> {
+    /// A reference resolved against `repo_url` through the mirror.
+    Remote(&'a str),
+    /// A local bundle or tarball, optionally with the tag to pick from it.
+    Artifact(&'a Artifact, Option<&'a str>),
+}
+
+impl Source<'_> {
+    /// What the deploy is called in logs, the lock and history until it is resolved.
+    fn label(&self) -> String {
+        match self {
+            Source::Remote(spec) => spec.to_string(),
+            Source::Artifact(_, Some(spec)) => spec.to_string(),
+            Source::Artifact(artifact, None) => artifact.path.display().to_string(),
+        }
+    }
+}
 
 pub struct DeploymentManager {
     docker: DockerClient,
     git: GitClient,
+    history: History,
+    traefik_api: Option<TraefikApi>,
     config: Config,
 }
 
@@ -12,14 +63,30 @@ impl DeploymentManager {
     pub fn new(config: Config) -> Self {
         Self {
             docker: DockerClient::new(config.socket_path.clone()),
-            git: GitClient,
+            git: Self::git_client(&config),
+            history: History::new(&config.clone_path),
+            traefik_api: config.traefik_api.clone().map(TraefikApi::new),
             config,
         }
     }
 
+    fn git_client(config: &Config) -> GitClient {
+        let mut git = GitClient::default().with_auth(config.git_auth.clone());
+        if let Some(subpath) = &config.config_subpath {
+            git = git.with_config_subpath(subpath.clone());
+        }
+        if config.verify_tags {
+            git = git.with_signature_policy(SignaturePolicy {
+                gpg_home: config.gpg_home.clone(),
+                allowed_signers: config.allowed_signers.clone(),
+            });
+        }
+        git
+    }
+
     /// Robustly extract the service name from a container.
     /// Prefer the Docker Compose label if present, otherwise parse the container name.
-    fn extract_service_name(container: &crate::types::Container) -> String {
+    fn extract_service_name(container: &Container) -> String {
         // Try Docker Compose label first
         if let Some(labels) = &container.labels {
             if let Some(service) = labels.get("com.docker.compose.service") {
@@ -27,7 +94,7 @@ impl DeploymentManager {
             }
         }
         // Fallback: parse from container name
-        if let Some(name) = container.names.get(0) {
+        if let Some(name) = container.names.first() {
             let name = name.trim_start_matches('/');
             // Try underscore split (compose v2 default: <project>_<service>_<index>)
             let underscore_parts: Vec<&str> = name.split('_').collect();
@@ -49,222 +116,967 @@ impl DeploymentManager {
         String::new()
     }
 
-    fn update_compose_file_volume_source(
+    /// Reads and parses each compose file, in merge order.
+    fn load_compose_files(compose_files: &[String]) -> Result<Vec<(String, Value)>> {
+        compose_files
+            .iter()
+            .map(|file| {
+                let content = std::fs::read_to_string(file).map_err(|e| Error::io(file, e))?;
+                let doc =
+                    serde_yaml::from_str(&content).map_err(|e| Error::compose_file(file, e))?;
+                Ok((content, doc))
+            })
+            .collect()
+    }
+
+    /// The first service in `doc` (or just `service`) with a volume mounted at
+    /// `mount_path`, with that volume's source.
+    fn find_volume(
+        doc: &Value,
+        service: Option<&str>,
+        mount_path: &str,
+    ) -> Option<(String, Option<String>)> {
+        let services = doc.get("services").and_then(Value::as_mapping)?;
+        for (svc_name, svc) in services.iter() {
+            let svc_name = svc_name.as_str().unwrap_or_default();
+            if service.is_some_and(|service| service != svc_name) {
+                continue;
+            }
+            let Some(vols) = svc.get("volumes").and_then(Value::as_sequence) else {
+                continue;
+            };
+            for vol in vols {
+                if let Some(s) = vol.as_str() {
+                    let parts: Vec<&str> = s.split(':').collect();
+                    if parts.len() >= 2 && parts[1] == mount_path {
+                        return Some((svc_name.to_string(), Some(parts[0].to_string())));
+                    }
+                } else if vol.get("target").and_then(Value::as_str) == Some(mount_path) {
+                    let source = vol.get("source").and_then(Value::as_str);
+                    return Some((svc_name.to_string(), source.map(str::to_string)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Reads the volume source the compose files declare for `mount_path`, if any. Later
+    /// files override earlier ones, as in `docker compose -f a.yml -f b.yml`.
+    fn compose_volume_source(compose_files: &[String], mount_path: &str) -> Result<Option<String>> {
+        Ok(Self::load_compose_files(compose_files)?
+            .iter()
+            .rev()
+            .find_map(|(_, doc)| Self::find_volume(doc, None, mount_path))
+            .and_then(|(_, source)| source))
+    }
+
+    /// Names the compose files in errors.
+    fn compose_label(compose_files: &[String]) -> String {
+        compose_files.join(", ")
+    }
+
+    /// Works out the edits that point the `mount_path` volume at `symlink_path`. Each
+    /// service is edited in the last compose file that mounts `mount_path` for it, since
+    /// that is the definition compose ends up using. Files that don't change are left out.
+    fn compose_updates(
+        compose_files: &[String],
+        symlink_path: &str,
+        mount_path: &str,
+        services: &[String],
+    ) -> Result<Vec<ComposeUpdate>> {
+        let docs = Self::load_compose_files(compose_files)?;
+        let mut targets: Vec<(usize, Vec<String>)> = Vec::new();
+        if services.is_empty() {
+            let owner = docs
+                .iter()
+                .rposition(|(_, doc)| Self::find_volume(doc, None, mount_path).is_some())
+                .unwrap_or(0);
+            targets.push((owner, Vec::new()));
+        } else {
+            let mut problems = Vec::new();
+            for name in services {
+                let owner = docs
+                    .iter()
+                    .rposition(|(_, doc)| Self::find_volume(doc, Some(name), mount_path).is_some());
+                match owner {
+                    Some(owner) => match targets.iter_mut().find(|(file, _)| *file == owner) {
+                        Some((_, names)) => names.push(name.clone()),
+                        None => targets.push((owner, vec![name.clone()])),
+                    },
+                    None if docs.iter().any(|(_, doc)| {
+                        doc.get("services")
+                            .and_then(|services| services.get(name.as_str()))
+                            .is_some()
+                    }) =>
+                    {
+                        problems.push(format!(
+                            "service '{}' has no volume mounted at {}",
+                            name, mount_path
+                        ))
+                    }
+                    None => problems.push(format!("service '{}' is not defined", name)),
+                }
+            }
+            if !problems.is_empty() {
+                return Err(Error::compose_file(
+                    Self::compose_label(compose_files),
+                    problems.join("; "),
+                ));
+            }
+        }
+
+        let mut updates = Vec::new();
+        for (owner, names) in targets {
+            let file = &compose_files[owner];
+            let original = &docs[owner].0;
+            let updated = Self::rewrite_compose_volume_source(
+                file,
+                original,
+                symlink_path,
+                mount_path,
+                &names,
+            )?;
+            if let Some(updated) = updated.filter(|updated| updated != original) {
+                updates.push(ComposeUpdate {
+                    file: file.clone(),
+                    original: original.clone(),
+                    updated,
+                });
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Computes the compose file content with the `mount_path` volume pointed at
+    /// `symlink_path`. Returns `None` when nothing needs to change. Only the volume
+    /// source is edited in the text; comments, anchors and formatting are kept.
+    ///
+    /// With `services` set, each of them must already mount `mount_path`. Otherwise the
+    /// first service that does is updated, or the volume is added to the first service
+    /// with a `volumes` list.
+    fn rewrite_compose_volume_source(
         compose_file: &str,
+        content: &str,
         symlink_path: &str,
         mount_path: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(compose_file)?;
-        let mut doc: Value = serde_yaml::from_str(&content)?;
-        let mut replaced = false;
-
-        if let Some(services) = doc.get_mut("services").and_then(Value::as_mapping_mut) {
-            for (_svc_name, svc) in services.iter_mut() {
-                if let Some(vols) = svc.get_mut("volumes").and_then(Value::as_sequence_mut) {
-                    // Try to find and update an existing mapping
-                    for vol in vols.iter_mut() {
-                        // Handle string form: "host:container[:mode]"
-                        if let Some(s) = vol.as_str() {
-                            let parts: Vec<&str> = s.split(':').collect();
-                            if parts.len() >= 2 {
-                                let target = parts[1];
-                                if target == mount_path && !replaced {
-                                    // preserve mode if present
-                                    let mut new_vol = format!("{}:{}", symlink_path, mount_path);
-                                    if parts.len() > 2 {
-                                        new_vol.push(':');
-                                        new_vol.push_str(parts[2]);
-                                    }
-                                    *vol = Value::String(new_vol);
-                                    replaced = true;
-                                }
-                            }
-                        }
-                        // Handle map form (YAML 1.2): {type: bind, source: ..., target: ...}
-                        else if let Some(map) = vol.as_mapping_mut() {
-                            if let Some(target) = map
-                                .get(&Value::String("target".to_string()))
-                                .and_then(Value::as_str)
-                            {
-                                if target == mount_path && !replaced {
-                                    map.insert(
-                                        Value::String("source".to_string()),
-                                        Value::String(symlink_path.to_string()),
-                                    );
-                                    replaced = true;
-                                }
-                            }
-                        }
-                        if replaced {
-                            break;
+        services: &[String],
+    ) -> Result<Option<String>> {
+        let mut doc: Value =
+            serde_yaml::from_str(content).map_err(|e| Error::compose_file(compose_file, e))?;
+        let mut edited = Some(content.to_string());
+
+        let mut declared = doc.get_mut("services").and_then(Value::as_mapping_mut);
+        if !services.is_empty() {
+            let mut problems = Vec::new();
+            for name in services {
+                match declared.as_mut().and_then(|d| d.get_mut(name.as_str())) {
+                    None => problems.push(format!("service '{}' is not defined", name)),
+                    Some(svc) => {
+                        if !Self::repoint_volume(name, svc, &mut edited, symlink_path, mount_path) {
+                            problems.push(format!(
+                                "service '{}' has no volume mounted at {}",
+                                name, mount_path
+                            ));
                         }
                     }
-                    // If not found, add a new mapping
-                    if !replaced {
-                        // Default to rw mode
-                        let new_vol = format!("{}:{}:rw", symlink_path, mount_path);
+                }
+            }
+            if !problems.is_empty() {
+                return Err(Error::compose_file(compose_file, problems.join("; ")));
+            }
+        } else {
+            let mut replaced = false;
+            for (svc_name, svc) in declared.into_iter().flat_map(|d| d.iter_mut()) {
+                let svc_name = svc_name.as_str().unwrap_or_default();
+                if !svc.get("volumes").is_some_and(Value::is_sequence) {
+                    continue;
+                }
+                replaced =
+                    Self::repoint_volume(svc_name, svc, &mut edited, symlink_path, mount_path);
+                // If not found, add a new mapping
+                if !replaced {
+                    // Default to rw mode
+                    let new_vol = format!("{}:{}:rw", symlink_path, mount_path);
+                    edited = edited
+                        .and_then(|text| compose_edit::append_volume(&text, svc_name, &new_vol));
+                    if let Some(vols) = svc.get_mut("volumes").and_then(Value::as_sequence_mut) {
                         vols.push(Value::String(new_vol));
-                        replaced = true;
                     }
+                    replaced = true;
+                }
+                break;
+            }
+            if !replaced {
+                return Ok(None);
+            }
+        }
+
+        // The text edit must mean exactly what the structural one does
+        match edited {
+            Some(updated)
+                if serde_yaml::from_str::<Value>(&updated).is_ok_and(|parsed| parsed == doc) =>
+            {
+                Ok(Some(updated))
+            }
+            _ => Err(Error::compose_file(
+                compose_file,
+                format!(
+                    "cannot update the volume for {} in place; write the service's volumes \
+                     as a block list without aliases",
+                    mount_path
+                ),
+            )),
+        }
+    }
+
+    /// Points the volume of service `svc_name` mounted at `mount_path` at `symlink_path`,
+    /// both in `svc` and in the file's `text`. Returns false if there is no such volume.
+    fn repoint_volume(
+        svc_name: &str,
+        svc: &mut Value,
+        text: &mut Option<String>,
+        symlink_path: &str,
+        mount_path: &str,
+    ) -> bool {
+        let Some(vols) = svc.get_mut("volumes").and_then(Value::as_sequence_mut) else {
+            return false;
+        };
+        for (index, vol) in vols.iter_mut().enumerate() {
+            // Handle string form: "host:container[:mode]"
+            if let Some(s) = vol.as_str() {
+                let parts: Vec<&str> = s.split(':').collect();
+                if parts.len() >= 2 && parts[1] == mount_path {
+                    // preserve mode if present
+                    let mut new_vol = format!("{}:{}", symlink_path, mount_path);
+                    if parts.len() > 2 {
+                        new_vol.push(':');
+                        new_vol.push_str(parts[2]);
+                    }
+                    *text = text.take().and_then(|text| {
+                        compose_edit::set_volume(
+                            &text,
+                            svc_name,
+                            index,
+                            VolumeEdit::Entry(&new_vol),
+                        )
+                    });
+                    *vol = Value::String(new_vol);
+                    return true;
                 }
-                if replaced {
-                    break;
+            }
+            // Handle map form (YAML 1.2): {type: bind, source: ..., target: ...}
+            else if let Some(map) = vol.as_mapping_mut() {
+                if map
+                    .get(Value::String("target".to_string()))
+                    .and_then(Value::as_str)
+                    == Some(mount_path)
+                {
+                    map.insert(
+                        Value::String("source".to_string()),
+                        Value::String(symlink_path.to_string()),
+                    );
+                    *text = text.take().and_then(|text| {
+                        compose_edit::set_volume(
+                            &text,
+                            svc_name,
+                            index,
+                            VolumeEdit::Source(symlink_path),
+                        )
+                    });
+                    return true;
                 }
             }
         }
-        if replaced {
-            let updated = serde_yaml::to_string(&doc)?;
-            std::fs::write(compose_file, updated)?;
+        false
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Runs a rolling deployment of `tag` under the deploy lock and records the attempt
+    /// in the history ledger. Returns the recorded entry on success.
+    pub async fn rolling_deploy(&self, tag: &str) -> Result<HistoryEntry> {
+        self.deploy(Source::Remote(tag)).await
+    }
+
+    /// Runs a rolling deployment from a local git bundle or tarball instead of `repo_url`.
+    /// `tag` picks the version from a bundle holding several; a tarball's manifest names it.
+    pub async fn deploy_artifact(
+        &self,
+        artifact: &Artifact,
+        tag: Option<&str>,
+    ) -> Result<HistoryEntry> {
+        self.deploy(Source::Artifact(artifact, tag)).await
+    }
+
+    async fn deploy(&self, source: Source<'_>) -> Result<HistoryEntry> {
+        let tag = source.label();
+        let tag = tag.as_str();
+        let mode = match self.config.strategy {
+            Strategy::Swarm => DeployMode::Swarm,
+            Strategy::Rolling => DeployMode::Compose,
+            Strategy::Reload => DeployMode::Reload,
+        };
+        let _lock = DeployLock::acquire(
+            &self.config.clone_path,
+            tag,
+            Duration::from_secs(self.config.lock_timeout),
+            self.config.break_lock,
+        )
+        .await?;
+        if let Some(repaired) = self.recover_current_symlink()? {
+            eprintln!(
+                "Repaired missing or dangling '{}/current' symlink to tag '{}'",
+                self.config.clone_path, repaired
+            );
+        }
+        let mut entry = HistoryEntry::start(tag, mode);
+        let result = self.run_rolling_deploy(&source, &mut entry).await;
+        entry.finish(&result);
+        if let Err(e) = self.history.append(&entry) {
+            eprintln!(
+                "Failed to record deployment in {}: {}",
+                self.history.path().display(),
+                e
+            );
+        }
+        result.map(|()| entry)
+    }
+
+    /// Resolves `source` to a concrete version: refreshes the mirror and resolves
+    /// branches, SHAs and semver requirements, or imports/unpacks a local artifact.
+    async fn prepare_target(&self, source: &Source<'_>) -> Result<DeployTarget> {
+        let config = &self.config;
+        match source {
+            Source::Remote(spec) => {
+                // A branch may have moved since its directory was checked out, so refresh
+                // even then; only an existing directory can do without the mirror.
+                if let Err(e) = self
+                    .git
+                    .update_mirror(&config.repo_url, &config.clone_path)
+                    .await
+                {
+                    if !Path::new(&GitClient::versioned_path(&config.clone_path, spec)).is_dir() {
+                        return Err(e);
+                    }
+                    eprintln!(
+                        "Could not create the mirror, resolving '{}' without it: {}",
+                        spec, e
+                    );
+                }
+                self.git
+                    .resolve_target(&config.repo_url, spec, &config.clone_path)
+            }
+            Source::Artifact(artifact, spec) => {
+                let target = self.artifact_target(artifact, *spec)?;
+                match artifact.kind {
+                    ArtifactKind::Bundle => {
+                        self.git
+                            .import_bundle(&config.repo_url, &artifact.path, &config.clone_path)
+                            .await?
+                    }
+                    ArtifactKind::Tarball => {
+                        artifact.unpack(&config.clone_path)?;
+                    }
+                }
+                Ok(target)
+            }
+        }
+    }
+
+    /// The commit a deployed version was built from. Unpacked tarballs have no git
+    /// metadata, so their manifest names it; asking git there would walk up to any
+    /// repository `clone_path` happens to sit in.
+    async fn deployed_commit(
+        &self,
+        source: &Source<'_>,
+        target: &DeployTarget,
+        versioned_path: &str,
+    ) -> Option<String> {
+        match source {
+            Source::Artifact(artifact, _) if artifact.kind == ArtifactKind::Tarball => {
+                target.commit.clone()
+            }
+            _ => match self.git.resolve_commit(versioned_path).await {
+                Ok(commit) => Some(commit),
+                Err(_) => target.commit.clone(),
+            },
+        }
+    }
+
+    /// The version an artifact holds, worked out without changing anything.
+    fn artifact_target(&self, artifact: &Artifact, spec: Option<&str>) -> Result<DeployTarget> {
+        match artifact.kind {
+            ArtifactKind::Bundle => {
+                let tags = self.git.bundle_tags(&artifact.path)?;
+                let tag = select_bundle_tag(&tags, spec)
+                    .map_err(|message| Error::artifact(&artifact.path, message))?;
+                Ok(DeployTarget::tag(spec.unwrap_or(&tag), &tag))
+            }
+            ArtifactKind::Tarball => {
+                if self.config.verify_tags {
+                    return Err(Error::Signature {
+                        tag: artifact.path.display().to_string(),
+                        reason:
+                            "tarballs carry no signature to verify; deploy a git bundle instead"
+                                .to_string(),
+                    });
+                }
+                let manifest = artifact.manifest()?;
+                if let Some(spec) = spec.filter(|spec| *spec != manifest.tag) {
+                    return Err(Error::artifact(
+                        &artifact.path,
+                        format!("contains '{}', not '{}'", manifest.tag, spec),
+                    ));
+                }
+                Ok(DeployTarget {
+                    requested: manifest.tag.clone(),
+                    name: manifest.tag,
+                    kind: TargetKind::Tag,
+                    commit: Some(manifest.commit),
+                })
+            }
         }
-        Ok(())
     }
 
-    pub async fn rolling_deploy(
+    async fn run_rolling_deploy(
         &self,
-        tag: &str,
-        swarm: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        source: &Source<'_>,
+        entry: &mut HistoryEntry,
+    ) -> Result<()> {
         let config = &self.config;
+        let spec = source.label();
         println!(
-            "Starting rolling deployment for project '{}' with tag '{}'",
-            config.name, tag
+            "Starting rolling deployment for project '{}' with '{}'",
+            config.name, spec
         );
 
+        // 0. Resolve the source to a concrete version
+        let target = self.prepare_target(source).await?;
+        if target.name != spec {
+            println!("Resolved '{}' to '{}'", spec, target.name);
+        }
+        entry.tag = target.name.clone();
+        let tag = target.name.as_str();
+
+        // Remember what is live now so a failed rollout can be reverted
+        let previous_target =
+            std::fs::read_link(Path::new(&config.clone_path).join("current")).ok();
+
         // 1. Clone the new configuration to a versioned directory
-        let symlink_path = self
+        let checkout = self
             .git
-            .clone_repository_to_versioned_path(&config.repo_url, tag, &config.clone_path)
+            .clone_repository_to_versioned_path(&config.repo_url, &target, &config.clone_path)
             .await?;
+        if let Some(signer) = &checkout.signer {
+            println!("Tag '{}' signed by {}", tag, signer);
+        }
+        entry.signer = checkout.signer;
+
+        // 1.1. Refuse a version Traefik would choke on before anything points at it
+        traefik::validate_dir(Path::new(&checkout.live_path))?;
+        let routers = match &self.traefik_api {
+            Some(_) => expected_routers(&DynamicConfig::load(Path::new(&checkout.live_path))?),
+            None => Vec::new(),
+        };
+        // Work out the compose edit up front, so a compose file that can't be updated
+        // stops the deploy before `current` moves
+        let compose_updates = if config.strategy == Strategy::Reload {
+            self.check_reload_mount()?;
+            Vec::new()
+        } else {
+            Self::compose_updates(
+                &config.compose_files,
+                &format!("{}/current", config.clone_path),
+                &config.mount_path,
+                &config.services,
+            )?
+        };
+        let switched_at = chrono::Utc::now().timestamp();
+        let symlink_path =
+            GitClient::switch_current(&config.clone_path, Path::new(&checkout.live_path))?;
+        let versioned_path = GitClient::versioned_path(&config.clone_path, tag);
+        entry.commit = self.deployed_commit(source, &target, &versioned_path).await;
+
+        if config.strategy == Strategy::Reload {
+            // 2. Traefik watches `current` itself; nothing is recreated
+            if let Err(e) = self.confirm_reload(switched_at, &routers, entry).await {
+                eprintln!("Traefik did not reload '{}': {}", tag, e);
+                return Err(self
+                    .revert(e, previous_target.as_deref(), &compose_updates, &[], entry)
+                    .await);
+            }
+            println!("Traefik reloaded '{}' without a restart", tag);
+            self.cleanup_old_configs(&config.clone_path, Path::new(&versioned_path))
+                .await?;
+            return Ok(());
+        }
 
         // 1.5. Update the compose file to use the new config path as the volume source
-        // NOTE: You must add serde_yaml = "*" to Cargo.toml
-        Self::update_compose_file_volume_source(
-            &config.compose_file,
-            &symlink_path,
-            &config.mount_path,
-        )?;
-
-        if swarm {
+        let written = compose_updates.iter().try_for_each(|update| {
+            std::fs::write(&update.file, &update.updated).map_err(|e| Error::io(&update.file, e))
+        });
+        if let Err(e) = written {
+            return Err(self
+                .revert(e, previous_target.as_deref(), &compose_updates, &[], entry)
+                .await);
+        }
+
+        if config.strategy == Strategy::Swarm {
             let service = &config.name;
             println!(
                 "Swarm mode: updating service '{}' mount to new config path.",
                 service
             );
-            let add_arg = format!("type=bind,src={},dst={}", symlink_path, config.mount_path);
-            let status = std::process::Command::new("docker")
-                .args([
-                    "service",
-                    "update",
-                    "--mount-rm",
-                    &config.mount_path,
-                    "--mount-add",
-                    &add_arg,
-                    service,
-                ])
-                .status()?;
-            if !status.success() {
-                return Err(format!("docker service update failed for service {}", service).into());
+            if let Err(e) = self.update_swarm_service(&symlink_path) {
+                eprintln!("Service {} failed to update: {}", service, e);
+                return Err(self
+                    .revert(e, previous_target.as_deref(), &compose_updates, &[], entry)
+                    .await);
             }
+            entry.containers.push(service.clone());
             println!("Successfully updated service '{}' in Swarm mode.", service);
         } else {
-            // 2. Find running Traefik containers for this project
-            let running_containers = self
-                .docker
-                .get_running_containers_by_image_substring(&config.name)
-                .await?;
+            // 2. Roll the configured services, or those of the running Traefik containers
+            let services = if config.services.is_empty() {
+                let running_containers = self.project_containers().await?;
 
-            if running_containers.is_empty() {
-                return Err(
-                    format!("No running containers found for project '{}'", config.name).into(),
+                if running_containers.is_empty() {
+                    return Err(Error::NoContainers {
+                        project: config.name.clone(),
+                        image: config.image.clone(),
+                    });
+                }
+
+                println!(
+                    "Found {} running Traefik containers",
+                    running_containers.len()
                 );
+                let mut services: Vec<String> = Vec::new();
+                for container in &running_containers {
+                    let service_name = Self::extract_service_name(container);
+                    if !services.contains(&service_name) {
+                        services.push(service_name);
+                    }
+                }
+                services
+            } else {
+                config.services.clone()
+            };
+
+            // 3. For each service, recreate it and wait for it to be healthy
+            let mut rolled = Vec::new();
+            for service_name in services {
+                println!("Rolling service: {}", service_name);
+
+                rolled.push(service_name.clone());
+                let result = match self.recreate_service(&service_name) {
+                    Ok(()) => match self.wait_for_healthy(&service_name).await {
+                        Ok(()) => self.verify_routers(&service_name, &routers).await,
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("Service {} failed to roll: {}", service_name, e);
+                    return Err(self
+                        .revert(
+                            e,
+                            previous_target.as_deref(),
+                            &compose_updates,
+                            &rolled,
+                            entry,
+                        )
+                        .await);
+                }
+
+                entry.containers.push(service_name.clone());
+                println!("Successfully rolled {} to new version", service_name);
             }
+        }
 
-            println!(
-                "Found {} running Traefik containers",
-                running_containers.len()
-            );
+        // 4. Clean up old config directories
+        self.cleanup_old_configs(&config.clone_path, Path::new(&versioned_path))
+            .await?;
 
-            // 3. For each running container, recreate the service
-            for (_index, container) in running_containers.iter().enumerate() {
-                let service_name = Self::extract_service_name(container);
-                println!("Rolling service: {}", service_name);
+        println!("Rolling deployment completed successfully!");
+        Ok(())
+    }
 
-                // Determine the absolute path to the compose file
-                let compose_file_abs = std::fs::canonicalize(&config.compose_file)?;
-                let compose_dir = compose_file_abs.parent().unwrap_or_else(|| Path::new("."));
+    /// Running containers whose image matches `config.image`, limited to the compose
+    /// project and to `config.services` when those are set.
+    async fn project_containers(&self) -> Result<Vec<Container>> {
+        let config = &self.config;
+        let containers = self
+            .docker
+            .get_running_containers_by_image_substring(&config.image)
+            .await?;
+        Ok(containers
+            .into_iter()
+            .filter(|container| {
+                config.compose_project.as_ref().is_none_or(|project| {
+                    container
+                        .labels
+                        .as_ref()
+                        .and_then(|labels| labels.get("com.docker.compose.project"))
+                        == Some(project)
+                })
+            })
+            .filter(|container| {
+                config.services.is_empty()
+                    || config
+                        .services
+                        .contains(&Self::extract_service_name(container))
+            })
+            .collect())
+    }
 
+    /// The `docker compose` arguments selecting this project's files, profiles and
+    /// project name, with the directory to run in (the first file's, as compose uses).
+    fn compose_command(&self) -> Result<(Vec<String>, PathBuf)> {
+        let config = &self.config;
+        let mut args = vec!["compose".to_string()];
+        let mut compose_dir = None;
+        for file in &config.compose_files {
+            let file_abs = std::fs::canonicalize(file).map_err(|e| Error::io(file, e))?;
+            if compose_dir.is_none() {
+                let dir = file_abs.parent().unwrap_or_else(|| Path::new("."));
                 // Check if the directory exists
-                if !compose_dir.exists() {
-                    return Err(format!(
-                        "Compose directory does not exist: {}",
-                        compose_dir.display()
-                    )
-                    .into());
+                if !dir.exists() {
+                    return Err(Error::compose_file(
+                        &file_abs,
+                        format!("directory {} does not exist", dir.display()),
+                    ));
                 }
+                compose_dir = Some(dir.to_path_buf());
+            }
+            args.push("-f".to_string());
+            args.push(file_abs.display().to_string());
+        }
+        if let Some(project) = &config.compose_project {
+            args.push("-p".to_string());
+            args.push(project.clone());
+        }
+        for profile in &config.compose_profiles {
+            args.push("--profile".to_string());
+            args.push(profile.clone());
+        }
+        Ok((args, compose_dir.unwrap_or_else(|| PathBuf::from("."))))
+    }
 
-                // Run docker compose up -d --force-recreate <service> in the compose file's directory
-                let status = std::process::Command::new("docker")
-                    .args([
-                        "compose",
-                        "-f",
-                        compose_file_abs.to_str().unwrap(),
-                        "up",
-                        "-d",
-                        "--force-recreate",
-                        service_name.as_str(),
-                    ])
-                    .current_dir(compose_dir)
-                    .status()?;
-
-                if !status.success() {
-                    return Err(
-                        format!("docker compose up failed for service {}", service_name).into(),
-                    );
+    /// Runs `docker compose up -d --force-recreate <service>` with the project's compose
+    /// files in the first file's directory.
+    fn recreate_service(&self, service_name: &str) -> Result<()> {
+        let (mut args, compose_dir) = self.compose_command()?;
+        args.extend(["up", "-d", "--force-recreate", service_name].map(str::to_string));
+
+        let output = std::process::Command::new("docker")
+            .args(&args)
+            .current_dir(compose_dir)
+            .output()
+            .map_err(|e| Error::io("docker", e))?;
+
+        if !output.status.success() {
+            return Err(Error::Command {
+                command: "docker compose up".to_string(),
+                service: service_name.to_string(),
+                exit_code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Waits until the newest container of `service_name` reports healthy through its
+    /// HEALTHCHECK, or, without one, stays running for the configured grace period.
+    async fn wait_for_healthy(&self, service_name: &str) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.health_timeout);
+        let grace_period = Duration::from_secs(self.config.health_grace_period);
+        let started = Instant::now();
+        let mut running_since: Option<Instant> = None;
+
+        println!(
+            "Waiting up to {}s for {} to become healthy",
+            timeout.as_secs(),
+            service_name
+        );
+
+        loop {
+            let containers = self
+                .docker
+                .get_containers_by_compose_service(
+                    service_name,
+                    self.config.compose_project.as_deref(),
+                )
+                .await?;
+            if let Some(container) = containers.first() {
+                let inspect = self.docker.inspect_container(&container.id).await?;
+                let state = &inspect.state;
+                match state.health.as_ref().map(|h| h.status.as_str()) {
+                    Some("healthy") => {
+                        println!("{} is healthy", service_name);
+                        return Ok(());
+                    }
+                    Some("unhealthy") => {
+                        return Err(Error::Health {
+                            service: service_name.to_string(),
+                            reason: "container reported unhealthy".to_string(),
+                        });
+                    }
+                    Some(_) => {}
+                    None if state.running => {
+                        let since = *running_since.get_or_insert_with(Instant::now);
+                        if since.elapsed() >= grace_period {
+                            println!(
+                                "{} has been running for {}s",
+                                service_name,
+                                grace_period.as_secs()
+                            );
+                            return Ok(());
+                        }
+                    }
+                    None if state.status == "exited" || state.status == "dead" => {
+                        return Err(Error::Health {
+                            service: service_name.to_string(),
+                            reason: format!("container stopped with exit code {}", state.exit_code),
+                        });
+                    }
+                    None => running_since = None,
                 }
+            }
 
-                println!("Successfully rolled {} to new version", service_name);
+            if started.elapsed() >= timeout {
+                return Err(Error::Health {
+                    service: service_name.to_string(),
+                    reason: format!("not healthy after {}s", timeout.as_secs()),
+                });
             }
+            tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
         }
+    }
 
-        // 4. Clean up old config directories (keep last 3 versions)
-        self.cleanup_old_configs(&config.clone_path, 3).await?;
+    /// Polls the Traefik API, if one is configured, until every router of the new
+    /// version is loaded with its new rule and enabled, within the health timeout.
+    async fn verify_routers(&self, service_name: &str, expected: &[ExpectedRouter]) -> Result<()> {
+        let Some(api) = &self.traefik_api else {
+            return Ok(());
+        };
+        let timeout = Duration::from_secs(self.config.health_timeout);
+        let started = Instant::now();
+        println!(
+            "Checking {} routers through the Traefik API at {}",
+            expected.len(),
+            api.address()
+        );
 
-        println!("Rolling deployment completed successfully!");
-        Ok(())
+        loop {
+            match api.check(expected).await {
+                Ok(()) => {
+                    println!("Traefik loaded all {} routers", expected.len());
+                    return Ok(());
+                }
+                Err(reason) if started.elapsed() >= timeout => {
+                    return Err(Error::Health {
+                        service: service_name.to_string(),
+                        reason: format!("Traefik API at {}: {}", api.address(), reason),
+                    });
+                }
+                Err(_) => tokio::time::sleep(HEALTH_POLL_INTERVAL).await,
+            }
+        }
     }
 
-    async fn cleanup_old_configs(
+    /// Restores the previous version after `cause` failed the rollout, and returns the
+    /// error to report: `RolledBack`, or `RollbackFailed` if restoring failed too.
+    async fn revert(
         &self,
-        base_path: &str,
-        keep_versions: usize,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut config_dirs = Vec::new();
-
-        if let Ok(entries) = std::fs::read_dir(base_path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                            if name.starts_with("traefik-config-") {
-                                config_dirs.push(path);
-                            }
-                        }
+        cause: Error,
+        previous_target: Option<&Path>,
+        compose_updates: &[ComposeUpdate],
+        services: &[String],
+        entry: &mut HistoryEntry,
+    ) -> Error {
+        match self
+            .restore_previous_version(previous_target, compose_updates, services)
+            .await
+        {
+            Ok(tag) => {
+                entry.outcome = Outcome::RolledBack;
+                Error::RolledBack {
+                    tag,
+                    cause: Box::new(cause),
+                }
+            }
+            Err(rollback) => Error::RollbackFailed {
+                cause: Box::new(cause),
+                rollback: Box::new(rollback),
+            },
+        }
+    }
+
+    /// The reload strategy needs containers to see the `current` symlink itself: a bind
+    /// mount of a symlink is resolved once, when the container starts. So the clone path
+    /// must be mounted at the same path, where `current`'s absolute target resolves too.
+    fn check_reload_mount(&self) -> Result<()> {
+        let config = &self.config;
+        let clone_path = config.clone_path.trim_end_matches('/');
+        match Self::compose_volume_source(&config.compose_files, clone_path)? {
+            Some(source) if source.trim_end_matches('/') == clone_path => Ok(()),
+            _ => Err(Error::compose_file(
+                Self::compose_label(&config.compose_files),
+                format!(
+                    "the reload strategy needs '{0}:{0}:ro' as a volume and Traefik's file \
+                     provider watching {0}/current; a mount of the config directory itself \
+                     only changes when the container is recreated",
+                    clone_path
+                ),
+            )),
+        }
+    }
+
+    /// Confirms that Traefik reloaded after `current` was switched at `since` (Unix
+    /// seconds): through the API if one is configured, otherwise from the containers' logs.
+    async fn confirm_reload(
+        &self,
+        since: i64,
+        routers: &[ExpectedRouter],
+        entry: &mut HistoryEntry,
+    ) -> Result<()> {
+        let config = &self.config;
+        if self.traefik_api.is_some() {
+            return self.verify_routers(&config.name, routers).await;
+        }
+
+        let mut pending = self.project_containers().await?;
+        if pending.is_empty() {
+            return Err(Error::NoContainers {
+                project: config.name.clone(),
+                image: config.image.clone(),
+            });
+        }
+        let timeout = Duration::from_secs(config.health_timeout);
+        let started = Instant::now();
+        println!(
+            "Waiting up to {}s for {} Traefik containers to log the reload",
+            timeout.as_secs(),
+            pending.len()
+        );
+
+        loop {
+            let mut waiting = Vec::new();
+            for container in pending {
+                let service = Self::extract_service_name(&container);
+                let logs = self.docker.container_logs(&container.id, since).await?;
+                match traefik::scan_reload_log(&logs) {
+                    ReloadLog::Loaded => {
+                        println!("{} reloaded its config", service);
+                        entry.containers.push(service);
+                    }
+                    ReloadLog::Failed(line) => {
+                        return Err(Error::Health {
+                            service,
+                            reason: format!("Traefik rejected the new config: {}", line),
+                        });
                     }
+                    ReloadLog::Pending => waiting.push(container),
                 }
             }
+            pending = waiting;
+            let Some(container) = pending.first() else {
+                return Ok(());
+            };
+            if started.elapsed() >= timeout {
+                return Err(Error::Health {
+                    service: Self::extract_service_name(container),
+                    reason: format!(
+                        "no file provider reload logged after {}s; Traefik logs it only at \
+                         debug level, or use --traefik-api",
+                        timeout.as_secs()
+                    ),
+                });
+            }
+            tokio::time::sleep(HEALTH_POLL_INTERVAL).await;
         }
+    }
 
-        // Sort by creation time (newest first)
-        config_dirs.sort_by_key(|path| {
-            std::fs::metadata(path)
-                .and_then(|m| m.created())
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-        });
-        config_dirs.reverse();
+    /// Points `current` and the compose file back at what was live before the rollout
+    /// and recreates `services` on it, waiting for each to be healthy again. Returns the
+    /// restored tag.
+    async fn restore_previous_version(
+        &self,
+        previous_target: Option<&Path>,
+        compose_updates: &[ComposeUpdate],
+        services: &[String],
+    ) -> Result<String> {
+        let config = &self.config;
+        let previous_target: PathBuf = previous_target
+            .ok_or_else(|| Error::NoPreviousVersion {
+                clone_path: config.clone_path.clone(),
+            })?
+            .to_path_buf();
+        let previous_tag = GitClient::tag_from_versioned_dir(&previous_target)
+            .unwrap_or_else(|| previous_target.display().to_string());
+        println!("Rolling back to previous version '{}'", previous_tag);
+
+        GitClient::switch_current(&config.clone_path, &previous_target)?;
+        for update in compose_updates {
+            std::fs::write(&update.file, &update.original)
+                .map_err(|e| Error::io(&update.file, e))?;
+        }
+
+        for service_name in services {
+            println!("Restoring service: {}", service_name);
+            self.recreate_service(service_name)?;
+            self.wait_for_healthy(service_name).await?;
+        }
 
+        Ok(previous_tag)
+    }
+
+    /// The `docker service update` command line that swaps the config mount in Swarm mode.
+    /// Runs [`Self::swarm_update_args`] for the configured Swarm service.
+    fn update_swarm_service(&self, symlink_path: &str) -> Result<()> {
+        let output = std::process::Command::new("docker")
+            .args(&self.swarm_update_args(symlink_path)[1..])
+            .output()
+            .map_err(|e| Error::io("docker", e))?;
+        if !output.status.success() {
+            return Err(Error::Command {
+                command: "docker service update".to_string(),
+                service: self.config.name.clone(),
+                exit_code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn swarm_update_args(&self, symlink_path: &str) -> Vec<String> {
+        let config = &self.config;
+        vec![
+            "docker".to_string(),
+            "service".to_string(),
+            "update".to_string(),
+            "--mount-rm".to_string(),
+            config.mount_path.clone(),
+            "--mount-add".to_string(),
+            format!("type=bind,src={},dst={}", symlink_path, config.mount_path),
+            config.name.clone(),
+        ]
+    }
+
+    /// Versioned config directories beyond the newest `KEEP_VERSIONS`, counting
+    /// `live` as the newest one whether or not it exists yet. `live` is never returned.
+    fn stale_config_dirs(base_path: &str, live: &Path) -> Vec<PathBuf> {
+        let mut config_dirs = GitClient::versioned_config_dirs(base_path);
+        config_dirs.retain(|dir| dir != live);
+        config_dirs.into_iter().skip(KEEP_VERSIONS - 1).collect()
+    }
+
+    async fn cleanup_old_configs(&self, base_path: &str, live: &Path) -> Result<()> {
         // Remove old versions beyond the keep limit
-        for old_config in config_dirs.iter().skip(keep_versions) {
+        for old_config in Self::stale_config_dirs(base_path, live).iter() {
             println!("Cleaning up old config: {:?}", old_config);
             if let Err(e) = std::fs::remove_dir_all(old_config) {
                 eprintln!("Failed to remove old config {:?}: {}", old_config, e);
@@ -274,35 +1086,790 @@ impl DeploymentManager {
         Ok(())
     }
 
-    pub async fn rollback(
+    /// Computes what deploying `spec` would do without changing anything. Refs are read
+    /// from the remote rather than the mirror, which a real deploy fetches first.
+    pub async fn plan(&self, spec: &str) -> Result<DeployPlan> {
+        self.plan_source(&Source::Remote(spec)).await
+    }
+
+    /// Computes what deploying from a local artifact would do without changing anything.
+    pub async fn plan_artifact(
+        &self,
+        artifact: &Artifact,
+        tag: Option<&str>,
+    ) -> Result<DeployPlan> {
+        self.plan_source(&Source::Artifact(artifact, tag)).await
+    }
+
+    async fn plan_source(&self, source: &Source<'_>) -> Result<DeployPlan> {
+        let config = &self.config;
+        let target = match source {
+            Source::Remote(spec) => {
+                self.git
+                    .resolve_target_remote(&config.repo_url, spec, &config.clone_path)?
+            }
+            Source::Artifact(artifact, spec) => self.artifact_target(artifact, *spec)?,
+        };
+        let tag = target.name.as_str();
+        let versioned_path = GitClient::versioned_path(&config.clone_path, tag);
+        let symlink_path = format!("{}/current", config.clone_path);
+
+        let fetch_commands = if Path::new(&versioned_path).exists() {
+            Vec::new()
+        } else {
+            match source {
+                Source::Remote(_) => self.git.fetch_commands(
+                    &config.repo_url,
+                    &target,
+                    &config.clone_path,
+                    &versioned_path,
+                ),
+                Source::Artifact(artifact, _) if artifact.kind == ArtifactKind::Bundle => {
+                    let mirror = GitClient::mirror_path(&config.clone_path);
+                    let mut commands = GitClient::bundle_import_commands(
+                        &config.repo_url,
+                        &mirror,
+                        &artifact.path,
+                        Path::new(&mirror).exists(),
+                    );
+                    commands.extend(self.git.checkout_commands(
+                        &target,
+                        &config.clone_path,
+                        &versioned_path,
+                    ));
+                    commands
+                }
+                Source::Artifact(artifact, _) => vec![artifact.unpack_args(&versioned_path)],
+            }
+        };
+
+        let reload = config.strategy == Strategy::Reload;
+        if reload {
+            self.check_reload_mount()?;
+        }
+        let compose_updates = if reload {
+            Vec::new()
+        } else {
+            Self::compose_updates(
+                &config.compose_files,
+                &symlink_path,
+                &config.mount_path,
+                &config.services,
+            )?
+        };
+        let compose_diff = (!compose_updates.is_empty()).then(|| {
+            compose_updates
+                .iter()
+                .map(|update| {
+                    similar::TextDiff::from_lines(&update.original, &update.updated)
+                        .unified_diff()
+                        .header(&update.file, &update.file)
+                        .to_string()
+                })
+                .collect::<String>()
+        });
+
+        let symlink = SymlinkChange {
+            from: std::fs::read_link(&symlink_path)
+                .ok()
+                .map(|p| p.display().to_string()),
+            path: symlink_path.clone(),
+            to: self.git.live_path(&config.clone_path, tag),
+        };
+
+        let (recreate, swarm_command) = if reload {
+            (Vec::new(), None)
+        } else if config.strategy == Strategy::Swarm {
+            (Vec::new(), Some(self.swarm_update_args(&symlink_path)))
+        } else {
+            let mut recreate = self
+                .project_containers()
+                .await?
+                .iter()
+                .map(|container| PlannedRecreate {
+                    container: container
+                        .names
+                        .first()
+                        .map(|n| n.trim_start_matches('/').to_string())
+                        .unwrap_or_else(|| container.id.clone()),
+                    service: Self::extract_service_name(container),
+                })
+                .collect::<Vec<_>>();
+            // Configured services are recreated even if nothing of theirs is running
+            for service in &config.services {
+                if !recreate.iter().any(|planned| planned.service == *service) {
+                    recreate.push(PlannedRecreate {
+                        container: "not running".to_string(),
+                        service: service.clone(),
+                    });
+                }
+            }
+            (recreate, None)
+        };
+
+        let cleanup = Self::stale_config_dirs(&config.clone_path, Path::new(&versioned_path))
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect();
+
+        Ok(DeployPlan {
+            project: config.name.clone(),
+            tag: tag.to_string(),
+            requested: target.requested.clone(),
+            fetch_commands,
+            versioned_path,
+            compose_diff,
+            symlink,
+            recreate,
+            swarm_command,
+            reload,
+            cleanup,
+        })
+    }
+
+    /// Compares the routing of two versions. `from` defaults to the version `current`
+    /// points at; versions that aren't checked out yet are fetched through the mirror
+    /// without switching `current`.
+    pub async fn diff(&self, from: Option<&str>, to: &str) -> Result<RouteDiff> {
+        let config = &self.config;
+        let (from_label, from_dir) = match from {
+            Some(spec) => self.checkout(spec).await?,
+            None => {
+                let current = Path::new(&config.clone_path).join("current");
+                let live = std::fs::read_link(&current).map_err(|_| {
+                    Error::Config(format!(
+                        "{} does not exist; pass the version to compare from",
+                        current.display()
+                    ))
+                })?;
+                let label = GitClient::current_tag(&config.clone_path)
+                    .unwrap_or_else(|| "current".to_string());
+                (label, live.display().to_string())
+            }
+        };
+        let (to_label, to_dir) = self.checkout(to).await?;
+
+        let old = DynamicConfig::load(Path::new(&from_dir))?;
+        let new = DynamicConfig::load(Path::new(&to_dir))?;
+        for problem in old.problems.iter().chain(&new.problems) {
+            eprintln!("Ignoring unreadable config: {}", problem);
+        }
+        Ok(RouteDiff::between(&from_label, &old, &to_label, &new))
+    }
+
+    /// Resolves `spec` and checks it out if needed, returning its name and live path.
+    async fn checkout(&self, spec: &str) -> Result<(String, String)> {
+        let config = &self.config;
+        let target = self.prepare_target(&Source::Remote(spec)).await?;
+        let checkout = self
+            .git
+            .clone_repository_to_versioned_path(&config.repo_url, &target, &config.clone_path)
+            .await?;
+        Ok((target.name, checkout.live_path))
+    }
+
+    /// Reports which config version `current` and the compose file point at,
+    /// and what each running container actually has mounted.
+    pub async fn status(&self) -> Result<StatusReport> {
+        let config = &self.config;
+        let symlink_path = Path::new(&config.clone_path).join("current");
+        let current_target = std::fs::canonicalize(&symlink_path).ok();
+
+        let running_containers = self.project_containers().await?;
+
+        let mut containers = Vec::new();
+        for container in &running_containers {
+            let started_at = self
+                .docker
+                .inspect_container(&container.id)
+                .await?
+                .state
+                .started_at;
+            let started_at = chrono::DateTime::parse_from_rfc3339(&started_at)
+                .ok()
+                .map(|t| t.with_timezone(&chrono::Utc));
+            let (mount_source, resolved, stale) =
+                self.container_config(&container.mounts, started_at, current_target.as_deref());
+            containers.push(ContainerStatus {
+                name: container
+                    .names
+                    .first()
+                    .map(|n| n.trim_start_matches('/').to_string())
+                    .unwrap_or_else(|| container.id.clone()),
+                service: Self::extract_service_name(container),
+                config_tag: resolved
+                    .as_deref()
+                    .and_then(GitClient::tag_from_versioned_dir),
+                mount_source,
+                stale,
+            });
+        }
+
+        Ok(StatusReport {
+            project: config.name.clone(),
+            mount_path: config.mount_path.clone(),
+            current_tag: GitClient::current_tag(&config.clone_path),
+            current_target: current_target.map(|p| p.display().to_string()),
+            compose_source: Self::compose_volume_source(&config.compose_files, &config.mount_path)?,
+            containers,
+        })
+    }
+
+    /// The config directory a container started at `started_at` has mounted from
+    /// `source`, if known, and whether it differs from `current_target`. Docker resolves
+    /// a symlinked source such as `current` once, when the container starts, so resolving
+    /// it now only says what the container runs if it started after the link was last
+    /// switched. A container started before that is on an older directory.
+    /// The source of the container's config mount, the versioned directory it serves
+    /// and whether that is stale. Under the reload strategy the container mounts
+    /// `clone_path` and Traefik follows `current` live, so it always serves `current`.
+    fn container_config(
         &self,
-        project_name: &str,
-        tag: &str,
-        config: &Config,
-        swarm: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        mounts: &[Mount],
+        started_at: Option<chrono::DateTime<chrono::Utc>>,
+        current_target: Option<&Path>,
+    ) -> (Option<String>, Option<PathBuf>, bool) {
+        let config = &self.config;
+        if config.strategy == Strategy::Reload {
+            let clone_path = config.clone_path.trim_end_matches('/');
+            let Some(mount) = mounts
+                .iter()
+                .find(|m| m.target.trim_end_matches('/') == clone_path)
+            else {
+                return (None, None, true);
+            };
+            let resolved = std::fs::canonicalize(Path::new(&mount.source).join("current")).ok();
+            let stale = resolved.is_none() || resolved.as_deref() != current_target;
+            return (Some(mount.source.clone()), resolved, stale);
+        }
+
+        let Some(mount) = mounts.iter().find(|m| m.target == config.mount_path) else {
+            return (None, None, true);
+        };
+        let (resolved, stale) =
+            Self::mounted_config(Path::new(&mount.source), started_at, current_target);
+        (Some(mount.source.clone()), resolved, stale)
+    }
+
+    fn mounted_config(
+        source: &Path,
+        started_at: Option<chrono::DateTime<chrono::Utc>>,
+        current_target: Option<&Path>,
+    ) -> (Option<PathBuf>, bool) {
+        let switched_at = std::fs::symlink_metadata(source)
+            .ok()
+            .filter(|meta| meta.file_type().is_symlink())
+            .map(|meta| meta.modified().map(chrono::DateTime::<chrono::Utc>::from));
+        let resolved = match switched_at {
+            Some(Ok(switched_at)) if started_at.is_some_and(|started| started >= switched_at) => {
+                std::fs::canonicalize(source).ok()
+            }
+            Some(_) => None,
+            None => std::fs::canonicalize(source).ok(),
+        };
+        let stale = match (&resolved, current_target) {
+            (Some(resolved), Some(current)) => resolved != current,
+            _ => true,
+        };
+        (resolved, stale)
+    }
+
+    /// Repairs a missing or dangling `current` symlink, e.g. after a crash mid-switch.
+    /// Points it at the most recent successfully deployed tag whose directory still exists,
+    /// falling back to the newest versioned directory. Returns the tag it was repaired to.
+    /// Only called with the deploy lock held, so it never races a rollout.
+    fn recover_current_symlink(&self) -> Result<Option<String>> {
+        let clone_path = &self.config.clone_path;
+        if !Path::new(clone_path).exists() || !GitClient::current_is_broken(clone_path) {
+            return Ok(None);
+        }
+
+        let from_history = self
+            .history
+            .successful_tags()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|tag| Path::new(&self.git.live_path(clone_path, tag)).is_dir());
+        let Some(tag) = from_history.or_else(|| {
+            GitClient::versioned_config_dirs(clone_path)
+                .first()
+                .and_then(|dir| GitClient::tag_from_versioned_dir(dir))
+        }) else {
+            return Ok(None);
+        };
+
+        GitClient::switch_current(clone_path, Path::new(&self.git.live_path(clone_path, &tag)))?;
+        Ok(Some(tag))
+    }
+
+    /// Finds the tag that was deployed before the one `current` points at.
+    /// Prefers the history ledger and falls back to the newest versioned directory.
+    fn previous_tag(&self) -> Option<String> {
+        let current = GitClient::current_tag(&self.config.clone_path);
+        let from_history = self
+            .history
+            .successful_tags()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|tag| Some(tag) != current.as_ref());
+        from_history.or_else(|| {
+            GitClient::versioned_config_dirs(&self.config.clone_path)
+                .iter()
+                .filter_map(|dir| GitClient::tag_from_versioned_dir(dir))
+                .find(|tag| Some(tag) != current.as_ref())
+        })
+    }
+
+    /// The tag a rollback goes to: `tag` if given, otherwise the previously deployed one.
+    pub fn rollback_target(&self, tag: Option<&str>) -> Result<String> {
+        match tag {
+            Some(tag) => Ok(tag.to_string()),
+            None => self.previous_tag().ok_or_else(|| Error::NoPreviousVersion {
+                clone_path: self.config.clone_path.clone(),
+            }),
+        }
+    }
+
+    pub async fn rollback(&self, tag: Option<&str>) -> Result<HistoryEntry> {
+        let config = &self.config;
+        let tag = self.rollback_target(tag)?;
         println!(
             "Starting rollback of project '{}' to tag '{}'",
-            project_name, tag
+            config.name, tag
         );
 
-        // Check if the target version already exists
-        let target_config_path = format!("{}/traefik-config-{}", config.clone_path, tag);
+        // Perform rolling deployment to the target tag; it reuses the existing
+        // versioned directory or clones it if it was cleaned up
+        let entry = self.rolling_deploy(&tag).await?;
 
-        if !std::path::Path::new(&target_config_path).exists() {
-            // If the config doesn't exist locally, clone it
-            println!("Target config not found locally, cloning...");
-            self.git
-                .clone_repository_to_versioned_path(&config.repo_url, tag, &config.clone_path)
-                .await?;
-        } else {
-            println!("Using existing config at {}", target_config_path);
+        println!("Rollback completed successfully!");
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rewrite_compose_volume_source_preserves_mode() {
+        let content = "# proxy\nservices:\n  traefik:\n    image: &image traefik:v3.1   # pinned\n    volumes:\n      - /opt/configs/traefik-config-v1:/etc/traefik/dynamic:ro\n";
+        let updated = DeploymentManager::rewrite_compose_volume_source(
+            "docker-compose.yml",
+            content,
+            "/opt/configs/current",
+            "/etc/traefik/dynamic",
+            &[],
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(updated, content.replace("traefik-config-v1:", "current:"));
+
+        // A volume list behind an alias can't be edited in place
+        let aliased = "x-volumes: &volumes\n  - /opt/configs/v1:/etc/traefik/dynamic\nservices:\n  traefik:\n    volumes: *volumes\n";
+        let err = DeploymentManager::rewrite_compose_volume_source(
+            "docker-compose.yml",
+            aliased,
+            "/opt/configs/current",
+            "/etc/traefik/dynamic",
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_rewrite_every_listed_service() {
+        let content = "services:\n  db:\n    volumes:\n      - data:/var/lib/db\n  edge-a:\n    volumes:\n      - /opt/configs/traefik-config-v1:/etc/traefik/dynamic:ro\n  edge-b:\n    volumes:\n      - type: bind\n        source: /opt/configs/traefik-config-v1\n        target: /etc/traefik/dynamic\n";
+        let rewrite = |services: &[&str]| {
+            let services: Vec<String> = services.iter().map(|s| s.to_string()).collect();
+            DeploymentManager::rewrite_compose_volume_source(
+                "docker-compose.yml",
+                content,
+                "/opt/configs/current",
+                "/etc/traefik/dynamic",
+                &services,
+            )
+        };
+
+        let updated = rewrite(&["edge-a", "edge-b"]).unwrap().unwrap();
+        assert_eq!(
+            updated,
+            content.replace("/opt/configs/traefik-config-v1", "/opt/configs/current")
+        );
+
+        // Listed services without the volume are refused instead of getting one injected
+        let err = rewrite(&["edge-a", "db", "edge-c"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Compose file docker-compose.yml: service 'db' has no volume mounted at /etc/traefik/dynamic; service 'edge-c' is not defined"
+        );
+    }
+
+    #[test]
+    fn test_stale_config_dirs_never_includes_live() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        for tag in ["v1", "v2", "v3", "v4"] {
+            std::fs::create_dir(GitClient::versioned_path(base, tag)).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
         }
+        // Redeploying the oldest version keeps it and drops the oldest of the rest
+        let live = GitClient::versioned_path(base, "v1");
+        let stale = DeploymentManager::stale_config_dirs(base, Path::new(&live));
+        assert_eq!(
+            stale,
+            vec![PathBuf::from(GitClient::versioned_path(base, "v2"))]
+        );
+    }
 
-        // Perform rolling deployment to the target tag
-        self.rolling_deploy(tag, swarm).await?;
+    fn test_config(clone_path: &str) -> Config {
+        Config {
+            repo_url: "https://example.com/configs.git".to_string(),
+            clone_path: clone_path.to_string(),
+            compose_files: vec![format!("{}/docker-compose.yml", clone_path)],
+            compose_profiles: Vec::new(),
+            compose_project: None,
+            mount_path: "/etc/traefik/dynamic".to_string(),
+            name: "traefik".to_string(),
+            image: "traefik".to_string(),
+            socket_path: "/var/run/docker.sock".to_string(),
+            health_timeout: 60,
+            health_grace_period: 10,
+            services: Vec::new(),
+            strategy: Strategy::Rolling,
+            lock_timeout: 0,
+            break_lock: false,
+            config_subpath: None,
+            verify_tags: false,
+            gpg_home: None,
+            allowed_signers: None,
+            git_auth: Default::default(),
+            traefik_api: None,
+        }
+    }
 
-        println!("Rollback completed successfully!");
-        Ok(())
+    #[tokio::test]
+    async fn test_tarball_commit_comes_from_manifest_inside_git_tree() {
+        let dir = tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        // `clone_path` lives inside an unrelated work tree
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "infra"]);
+        let clone_path = dir.path().join("configs");
+        std::fs::create_dir(&clone_path).unwrap();
+        let base = clone_path.to_str().unwrap();
+
+        let src = dir.path().join("src");
+        std::fs::create_dir(&src).unwrap();
+        std::fs::write(src.join("routes.yml"), "http: {}\n").unwrap();
+        std::fs::write(
+            src.join(crate::artifact::MANIFEST_FILE),
+            r#"{"tag": "v2.0.0", "commit": "0123456789abcdef"}"#,
+        )
+        .unwrap();
+        let archive = dir.path().join("config.tar.gz");
+        let status = std::process::Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&src)
+            .arg(".")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let manager = DeploymentManager::new(test_config(base));
+        let artifact = Artifact::open(&archive).unwrap();
+        let source = Source::Artifact(&artifact, None);
+        let target = manager.prepare_target(&source).await.unwrap();
+        let versioned_path = GitClient::versioned_path(base, &target.name);
+        assert!(Path::new(&versioned_path).join("routes.yml").exists());
+        assert_eq!(
+            manager
+                .deployed_commit(&source, &target, &versioned_path)
+                .await
+                .as_deref(),
+            Some("0123456789abcdef")
+        );
+    }
+
+    #[test]
+    fn test_container_started_before_switch_is_stale() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        for tag in ["v1", "v2"] {
+            std::fs::create_dir(GitClient::versioned_path(base, tag)).unwrap();
+        }
+        let v1 = GitClient::versioned_path(base, "v1");
+        let v2 = std::fs::canonicalize(GitClient::versioned_path(base, "v2")).unwrap();
+        let current = GitClient::switch_current(base, Path::new(&v1)).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let started = chrono::Utc::now();
+        std::thread::sleep(Duration::from_millis(20));
+
+        // `current` moves on after the container resolved it at start
+        GitClient::switch_current(base, &v2).unwrap();
+        let (resolved, stale) =
+            DeploymentManager::mounted_config(Path::new(&current), Some(started), Some(&v2));
+        assert_eq!(resolved, None);
+        assert!(stale);
+
+        // A container started after the switch runs what `current` points at
+        let restarted = chrono::Utc::now();
+        let (resolved, stale) =
+            DeploymentManager::mounted_config(Path::new(&current), Some(restarted), Some(&v2));
+        assert_eq!(resolved.as_deref(), Some(v2.as_path()));
+        assert!(!stale);
+
+        // A versioned directory mounted directly is compared as is
+        let (resolved, stale) =
+            DeploymentManager::mounted_config(Path::new(&v1), Some(restarted), Some(&v2));
+        assert_eq!(resolved, Some(std::fs::canonicalize(&v1).unwrap()));
+        assert!(stale);
+    }
+
+    #[test]
+    fn test_reload_status_follows_current_through_clone_path() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        for tag in ["v1", "v2"] {
+            std::fs::create_dir(GitClient::versioned_path(base, tag)).unwrap();
+        }
+        GitClient::switch_current(base, Path::new(&GitClient::versioned_path(base, "v1"))).unwrap();
+        let started = chrono::Utc::now();
+        std::thread::sleep(Duration::from_millis(20));
+        let v2 = std::fs::canonicalize(GitClient::versioned_path(base, "v2")).unwrap();
+        GitClient::switch_current(base, &v2).unwrap();
+
+        let mut config = test_config(base);
+        config.strategy = Strategy::Reload;
+        let manager = DeploymentManager::new(config);
+        let mounts: Vec<Mount> = serde_json::from_value(serde_json::json!([{
+            "Destination": base,
+            "Source": base,
+            "Type": "bind",
+            "Mode": "ro",
+            "RW": false,
+            "Propagation": "rprivate",
+        }]))
+        .unwrap();
+
+        // Traefik picked up the switch without a restart
+        let (source, resolved, stale) = manager.container_config(&mounts, Some(started), Some(&v2));
+        assert_eq!(source.as_deref(), Some(base));
+        assert_eq!(resolved.as_deref(), Some(v2.as_path()));
+        assert!(!stale);
+
+        // Without the clone_path mount the container can't see `current`
+        let (source, _, stale) = manager.container_config(&[], Some(started), Some(&v2));
+        assert_eq!(source, None);
+        assert!(stale);
+    }
+
+    #[test]
+    fn test_rollback_without_tag_never_picks_current() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        let manager = DeploymentManager::new(test_config(base));
+        for tag in ["v1.0.0", "v1.1.0"] {
+            std::fs::create_dir(GitClient::versioned_path(base, tag)).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(manager.history().entries().unwrap().is_empty());
+
+        // No history: the newest directory that `current` doesn't point at
+        GitClient::switch_current(base, Path::new(&GitClient::versioned_path(base, "v1.1.0")))
+            .unwrap();
+        assert_eq!(manager.previous_tag().as_deref(), Some("v1.0.0"));
+        assert_eq!(manager.rollback_target(None).unwrap(), "v1.0.0");
+
+        GitClient::switch_current(base, Path::new(&GitClient::versioned_path(base, "v1.0.0")))
+            .unwrap();
+        assert_eq!(manager.rollback_target(None).unwrap(), "v1.1.0");
+        assert_eq!(manager.rollback_target(Some("v0.9.0")).unwrap(), "v0.9.0");
+    }
+
+    #[test]
+    fn test_recover_current_symlink_from_history() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        let manager = DeploymentManager::new(test_config(base));
+        for tag in ["v1", "v2", "v3"] {
+            std::fs::create_dir(GitClient::versioned_path(base, tag)).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        for (tag, result) in [("v1", Ok(())), ("v2", Ok(())), ("v3", Err("unhealthy"))] {
+            let mut entry = HistoryEntry::start(tag, DeployMode::Compose);
+            entry.finish(&result);
+            manager.history().append(&entry).unwrap();
+        }
+
+        // Nothing to do while `current` is intact
+        GitClient::switch_current(base, Path::new(&GitClient::versioned_path(base, "v3"))).unwrap();
+        assert_eq!(manager.recover_current_symlink().unwrap(), None);
+
+        // Missing link goes back to the last successful deploy, not the newest directory
+        std::fs::remove_file(dir.path().join("current")).unwrap();
+        assert_eq!(
+            manager.recover_current_symlink().unwrap().as_deref(),
+            Some("v2")
+        );
+        assert_eq!(GitClient::current_tag(base).as_deref(), Some("v2"));
+
+        // Dangling link skips tags whose directory was cleaned up
+        std::fs::remove_dir(GitClient::versioned_path(base, "v2")).unwrap();
+        assert_eq!(
+            manager.recover_current_symlink().unwrap().as_deref(),
+            Some("v1")
+        );
+    }
+
+    #[test]
+    fn test_compose_volume_source_short_and_long_syntax() {
+        let dir = tempdir().unwrap();
+        let compose = dir.path().join("docker-compose.yml");
+        std::fs::write(
+            &compose,
+            "services:\n  db:\n    volumes:\n      - data:/var/lib/db\n  traefik:\n    volumes:\n      - type: bind\n        source: /opt/configs/current\n        target: /etc/traefik/dynamic\n",
+        )
+        .unwrap();
+        let compose = &[compose.to_str().unwrap().to_string()];
+
+        assert_eq!(
+            DeploymentManager::compose_volume_source(compose, "/etc/traefik/dynamic").unwrap(),
+            Some("/opt/configs/current".to_string())
+        );
+        assert_eq!(
+            DeploymentManager::compose_volume_source(compose, "/var/lib/db").unwrap(),
+            Some("data".to_string())
+        );
+        assert_eq!(
+            DeploymentManager::compose_volume_source(compose, "/missing").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compose_override_files_and_profiles() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        let compose = dir.path().join("docker-compose.yml");
+        let prod = dir.path().join("docker-compose.prod.yml");
+        std::fs::write(
+            &compose,
+            "services:\n  traefik:\n    image: traefik:v3.1\n    volumes:\n      - ./dynamic:/etc/traefik/dynamic:ro\n  internal:\n    image: traefik:v3.1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &prod,
+            "# production paths\nservices:\n  traefik:\n    volumes:\n      - /opt/configs/traefik-config-v1:/etc/traefik/dynamic:ro\n",
+        )
+        .unwrap();
+        let mut config = test_config(base);
+        config.compose_files = vec![
+            compose.to_str().unwrap().to_string(),
+            prod.to_str().unwrap().to_string(),
+        ];
+        config.compose_profiles = vec!["edge".to_string()];
+        config.compose_project = Some("proxy".to_string());
+
+        // The override wins, so that is the file edited
+        let updates = DeploymentManager::compose_updates(
+            &config.compose_files,
+            "/opt/configs/current",
+            "/etc/traefik/dynamic",
+            &["traefik".to_string()],
+        )
+        .unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].file, config.compose_files[1]);
+        assert_eq!(
+            updates[0].updated,
+            "# production paths\nservices:\n  traefik:\n    volumes:\n      - /opt/configs/current:/etc/traefik/dynamic:ro\n"
+        );
+        assert_eq!(
+            DeploymentManager::compose_volume_source(&config.compose_files, "/etc/traefik/dynamic")
+                .unwrap()
+                .as_deref(),
+            Some("/opt/configs/traefik-config-v1")
+        );
+        let err = DeploymentManager::compose_updates(
+            &config.compose_files,
+            "/opt/configs/current",
+            "/etc/traefik/dynamic",
+            &["internal".to_string()],
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("service 'internal' has no volume mounted at /etc/traefik/dynamic"),
+            "{}",
+            err
+        );
+
+        let manager = DeploymentManager::new(config);
+        let (args, compose_dir) = manager.compose_command().unwrap();
+        let dir_abs = std::fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(compose_dir, dir_abs);
+        let expected: Vec<String> = vec![
+            "compose".to_string(),
+            "-f".to_string(),
+            dir_abs.join("docker-compose.yml").display().to_string(),
+            "-f".to_string(),
+            dir_abs
+                .join("docker-compose.prod.yml")
+                .display()
+                .to_string(),
+            "-p".to_string(),
+            "proxy".to_string(),
+            "--profile".to_string(),
+            "edge".to_string(),
+        ];
+        assert_eq!(args, expected);
+    }
+
+    #[test]
+    fn test_reload_needs_clone_path_mounted_in_place() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        let mut config = test_config(base);
+        config.strategy = Strategy::Reload;
+        let manager = DeploymentManager::new(config);
+
+        std::fs::write(
+            dir.path().join("docker-compose.yml"),
+            format!(
+                "services:\n  traefik:\n    volumes:\n      - {}/current:/etc/traefik/dynamic:ro\n",
+                base
+            ),
+        )
+        .unwrap();
+        let err = manager.check_reload_mount().unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("{0}:{0}:ro", base)),
+            "{}",
+            err
+        );
+
+        std::fs::write(
+            dir.path().join("docker-compose.yml"),
+            format!(
+                "services:\n  traefik:\n    volumes:\n      - {0}:{0}:ro\n",
+                base
+            ),
+        )
+        .unwrap();
+        assert!(manager.check_reload_mount().is_ok());
     }
 }
@@ -1,92 +1,156 @@
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
+use std::time::Duration;
 
-use crate::types::Container;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
 
+use crate::error::{Error, Result};
+use crate::http::{self, HttpError, Response};
+use crate::types::{Container, ContainerInspect};
+
+/// Builds the error for a non-2xx response. Docker reports errors as `{"message": "..."}`.
+fn api_error(method: &str, endpoint: &str, response: &Response) -> Error {
+    let message = serde_json::from_slice::<serde_json::Value>(&response.body)
+        .ok()
+        .and_then(|v| {
+            v.get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| {
+            let text = response.text();
+            if text.trim().is_empty() {
+                response.reason.clone()
+            } else {
+                text.trim().to_string()
+            }
+        });
+    Error::DockerApi {
+        method: method.to_string(),
+        endpoint: endpoint.to_string(),
+        status: response.status,
+        message,
+    }
+}
+
+/// Default upper bound for a single Docker API request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Async Docker Engine API client. Each request uses its own connection, so the
+/// client can be cloned and used from several tasks at once; dropping a request
+/// future cancels it and closes its connection.
+#[derive(Clone)]
 pub struct DockerClient {
     socket_path: String,
+    timeout: Duration,
 }
 
 impl DockerClient {
     pub fn new(socket_path: String) -> Self {
-        Self { socket_path }
+        Self {
+            socket_path,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
     }
 
-    async fn api_call(&self, endpoint: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let stream = UnixStream::connect(&self.socket_path)?;
-        self.send_request(stream, endpoint).await
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
     }
 
-    async fn send_request(
-        &self,
-        mut stream: UnixStream,
-        endpoint: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let request = format!(
-            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
-            endpoint
-        );
-
-        stream.write_all(request.as_bytes())?;
-        self.read_response(stream)
+    /// Sends one request over a fresh connection and returns the raw response.
+    async fn request(&self, method: &str, endpoint: &str, body: Option<&[u8]>) -> Result<Response> {
+        let exchange = async {
+            let mut stream = UnixStream::connect(&self.socket_path).await?;
+            let request = http::encode_request(
+                method,
+                endpoint,
+                "localhost",
+                body.map(|body| ("application/json", body)),
+            );
+            stream.write_all(&request).await?;
+            http::read_response(&mut BufReader::new(stream), method).await
+        };
+        match tokio::time::timeout(self.timeout, exchange).await {
+            Ok(result) => result.map_err(|source: HttpError| Error::DockerConnection {
+                method: method.to_string(),
+                endpoint: endpoint.to_string(),
+                source,
+            }),
+            Err(_) => Err(Error::DockerTimeout {
+                method: method.to_string(),
+                endpoint: endpoint.to_string(),
+                timeout: self.timeout,
+            }),
+        }
     }
 
-    fn read_response(&self, mut stream: UnixStream) -> Result<String, Box<dyn std::error::Error>> {
-        let mut response = String::new();
-        stream.read_to_string(&mut response)?;
-
-        // Clean up HTTP chunked encoding and extract JSON body
-        if let Some(json_start) = response.find("\r\n\r\n") {
-            let body = &response[json_start + 4..];
-            // Handle chunked encoding - remove chunk size markers
-            Ok(self.clean_chunked_response(body))
-        } else {
+    /// Sends a request and turns any non-2xx status (other than those in `allowed`)
+    /// into `Error::DockerApi`.
+    async fn call(&self, method: &str, endpoint: &str, allowed: &[u16]) -> Result<Response> {
+        let response = self.request(method, endpoint, None).await?;
+        if response.is_success() || allowed.contains(&response.status) {
             Ok(response)
+        } else {
+            Err(api_error(method, endpoint, &response))
         }
     }
 
-    fn clean_chunked_response(&self, body: &str) -> String {
-        // Remove HTTP chunked encoding artifacts
-        let mut cleaned = body.to_string();
-
-        // Remove chunk size at the beginning (like "f053\r\n")
-        if let Some(first_newline) = cleaned.find("\r\n") {
-            if cleaned[..first_newline]
-                .chars()
-                .all(|c| c.is_ascii_hexdigit())
-            {
-                cleaned = cleaned[first_newline + 2..].to_string();
-            }
-        }
-
-        // Remove trailing chunk markers (like "\r\n0\r\n\r\n")
-        if cleaned.ends_with("\r\n0\r\n\r\n") {
-            cleaned.truncate(cleaned.len() - 7);
-        } else if cleaned.ends_with("\n\r\n0\r\n\r\n") {
-            cleaned.truncate(cleaned.len() - 8);
-        }
-
-        cleaned
+    async fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        let response = self.call("GET", endpoint, &[]).await?;
+        serde_json::from_slice(&response.body).map_err(|source| Error::DockerResponse {
+            endpoint: endpoint.to_string(),
+            source,
+        })
     }
 
-    pub async fn list_containers(
-        &self,
-        all: bool,
-    ) -> Result<Vec<Container>, Box<dyn std::error::Error>> {
+    pub async fn list_containers(&self, all: bool) -> Result<Vec<Container>> {
         let endpoint = if all {
             "/containers/json?all=true"
         } else {
             "/containers/json"
         };
-        let json_response = self.api_call(endpoint).await?;
-        let containers: Vec<Container> = serde_json::from_str(&json_response)?;
+        self.get_json(endpoint).await
+    }
+
+    pub async fn inspect_container(&self, container_id: &str) -> Result<ContainerInspect> {
+        self.get_json(&format!("/containers/{}/json", container_id))
+            .await
+    }
+
+    /// Containers (in any state) created for the given Docker Compose service, newest first,
+    /// limited to compose project `project` when given.
+    pub async fn get_containers_by_compose_service(
+        &self,
+        service: &str,
+        project: Option<&str>,
+    ) -> Result<Vec<Container>> {
+        let mut containers: Vec<Container> = self
+            .list_containers(true)
+            .await?
+            .into_iter()
+            .filter(|container| {
+                let label = |key: &str| {
+                    container
+                        .labels
+                        .as_ref()
+                        .and_then(|labels| labels.get(key))
+                        .cloned()
+                };
+                label("com.docker.compose.service").is_some_and(|s| s == service)
+                    && project.is_none_or(|project| {
+                        label("com.docker.compose.project").is_some_and(|p| p == project)
+                    })
+            })
+            .collect();
+        containers.sort_by_key(|container| std::cmp::Reverse(container.created));
         Ok(containers)
     }
 
     pub async fn get_running_containers_by_image_substring(
         &self,
         image_substring: &str,
-    ) -> Result<Vec<Container>, Box<dyn std::error::Error>> {
+    ) -> Result<Vec<Container>> {
         let containers = self.list_containers(true).await?;
         Ok(containers
             .into_iter()
@@ -96,71 +160,37 @@ impl DockerClient {
             .collect())
     }
 
-    pub async fn remove_container(
-        &self,
-        container_id: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn remove_container(&self, container_id: &str) -> Result<()> {
         let endpoint = &format!("/containers/{}?force=true", container_id);
-        let stream = UnixStream::connect(&self.socket_path)?;
-        self.send_delete_request(stream, endpoint).await?;
+        self.call("DELETE", endpoint, &[]).await?;
         Ok(())
     }
 
-    async fn send_delete_request(
-        &self,
-        mut stream: UnixStream,
-        endpoint: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let request = format!(
-            "DELETE {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
-            endpoint
-        );
-
-        stream.write_all(request.as_bytes())?;
-        self.read_response(stream)
-    }
-
-    pub async fn stop_container(
-        &self,
-        container_id: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn stop_container(&self, container_id: &str) -> Result<()> {
         let endpoint = &format!("/containers/{}/stop", container_id);
-        let stream = UnixStream::connect(&self.socket_path)?;
-        self.send_post_request(stream, endpoint, "").await?;
+        // 304: container already stopped
+        self.call("POST", endpoint, &[304]).await?;
         Ok(())
     }
 
-    pub async fn start_container(
-        &self,
-        container_id: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn start_container(&self, container_id: &str) -> Result<()> {
         let endpoint = &format!("/containers/{}/start", container_id);
-        let stream = UnixStream::connect(&self.socket_path)?;
-        self.send_post_request(stream, endpoint, "").await?;
+        // 304: container already started
+        self.call("POST", endpoint, &[304]).await?;
         Ok(())
     }
 
-    async fn send_post_request(
-        &self,
-        mut stream: UnixStream,
-        endpoint: &str,
-        body: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let request = format!(
-            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-            endpoint,
-            body.len(),
-            body
+    /// Output a container wrote to stdout and stderr since `since` (Unix seconds).
+    pub async fn container_logs(&self, container_id: &str, since: i64) -> Result<String> {
+        let endpoint = &format!(
+            "/containers/{}/logs?stdout=true&stderr=true&since={}",
+            container_id, since
         );
-
-        stream.write_all(request.as_bytes())?;
-        self.read_response(stream)
+        let response = self.call("GET", endpoint, &[]).await?;
+        Ok(demux_logs(&response.body))
     }
 
-    pub async fn get_running_containers_by_name(
-        &self,
-        name: &str,
-    ) -> Result<Vec<Container>, Box<dyn std::error::Error>> {
+    pub async fn get_running_containers_by_name(&self, name: &str) -> Result<Vec<Container>> {
         let containers = self.list_containers(true).await?;
         Ok(containers
             .into_iter()
@@ -170,3 +200,114 @@ impl DockerClient {
             .collect())
     }
 }
+
+/// Joins the frames of a multiplexed log stream (`[stream, 0, 0, 0, len: u32 BE]`
+/// followed by `len` bytes). Containers with a TTY send plain text instead.
+fn demux_logs(body: &[u8]) -> String {
+    let mut text = Vec::new();
+    let mut rest = body;
+    while let [0..=2, 0, 0, 0, a, b, c, d, frame @ ..] = rest {
+        let len = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+        if len > frame.len() {
+            break;
+        }
+        text.extend_from_slice(&frame[..len]);
+        rest = &frame[len..];
+    }
+    if text.is_empty() || !rest.is_empty() {
+        // Not multiplexed after all
+        return String::from_utf8_lossy(body).into_owned();
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::UnixListener;
+
+    /// Serves `responses` in order, one per connection, and returns the socket path.
+    /// Every request is read before any response is written, so a client that
+    /// blocks on one request would never see the others answered.
+    fn serve(dir: &std::path::Path, responses: Vec<&'static [u8]>) -> String {
+        let socket_path = dir.join("docker.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            for _ in 0..responses.len() {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await;
+                streams.push(stream);
+            }
+            for (mut stream, response) in streams.into_iter().zip(responses) {
+                stream.write_all(response).await.unwrap();
+            }
+        });
+        socket_path.display().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_non_2xx_is_a_typed_error() {
+        let dir = tempdir().unwrap();
+        let socket = serve(
+            dir.path(),
+            vec![b"HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: 39\r\n\r\n{\"message\":\"No such container: abc123\"}"],
+        );
+        let client = DockerClient::new(socket);
+        match client.stop_container("abc123").await.unwrap_err() {
+            Error::DockerApi {
+                status,
+                endpoint,
+                message,
+                ..
+            } => {
+                assert_eq!(status, 404);
+                assert_eq!(endpoint, "/containers/abc123/stop");
+                assert_eq!(message, "No such container: abc123");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests() {
+        let dir = tempdir().unwrap();
+        let list: &'static [u8] =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\n[\r\n1\r\n]\r\n0\r\n\r\n";
+        let socket = serve(dir.path(), vec![list, list]);
+        let client = DockerClient::new(socket);
+        let (first, second) =
+            tokio::join!(client.list_containers(true), client.list_containers(false));
+        assert!(first.unwrap().is_empty());
+        assert!(second.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_demux_logs() {
+        let mut body = vec![1, 0, 0, 0, 0, 0, 0, 6];
+        body.extend_from_slice(b"hello\n");
+        body.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 4]);
+        body.extend_from_slice(b"err\n");
+        assert_eq!(demux_logs(&body), "hello\nerr\n");
+        assert_eq!(demux_logs(b"plain tty output\n"), "plain tty output\n");
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let dir = tempdir().unwrap();
+        let socket_path = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(async move {
+            // Accept and never answer
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        let client = DockerClient::new(socket_path.display().to_string())
+            .with_timeout(Duration::from_millis(50));
+        let err = client.list_containers(true).await.unwrap_err();
+        assert!(matches!(err, Error::DockerTimeout { .. }));
+    }
+}
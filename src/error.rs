@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::http::HttpError;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Every failure the library can report, grouped by what went wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Missing or invalid configuration (CLI flags, `.env`).
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("I/O error on {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A `git` invocation exited unsuccessfully.
+    #[error("git {operation} failed{}{}: {}", tag.as_ref().map(|t| format!(" for tag '{}'", t)).unwrap_or_default(), exit_code.map(|c| format!(" (exit code {})", c)).unwrap_or_default(), stderr.trim())]
+    Git {
+        operation: String,
+        tag: Option<String>,
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+
+    /// The compose file could not be read, parsed or rewritten.
+    #[error("Compose file {}: {message}", path.display())]
+    ComposeFile { path: PathBuf, message: String },
+
+    /// A `docker compose` or `docker service` command exited unsuccessfully.
+    #[error("{command} failed for service '{service}'{}{}", exit_code.map(|c| format!(" (exit code {})", c)).unwrap_or_default(), if stderr.trim().is_empty() { String::new() } else { format!(": {}", stderr.trim()) })]
+    Command {
+        command: String,
+        service: String,
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+
+    /// The Docker Engine API answered with a non-2xx status.
+    #[error("Docker API {method} {endpoint} returned {status}: {message}")]
+    DockerApi {
+        method: String,
+        endpoint: String,
+        status: u16,
+        message: String,
+    },
+
+    /// The Docker Engine API could not be reached or spoke invalid HTTP.
+    #[error("Docker API {method} {endpoint} failed: {source}")]
+    DockerConnection {
+        method: String,
+        endpoint: String,
+        #[source]
+        source: HttpError,
+    },
+
+    #[error("Docker API {method} {endpoint} timed out after {}s", timeout.as_secs_f32())]
+    DockerTimeout {
+        method: String,
+        endpoint: String,
+        timeout: Duration,
+    },
+
+    /// The Docker Engine API returned a body that did not match the expected JSON.
+    #[error("Unexpected response from Docker API {endpoint}: {source}")]
+    DockerResponse {
+        endpoint: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error(
+        "No running containers with an image matching '{image}' found for project '{project}'"
+    )]
+    NoContainers { project: String, image: String },
+
+    /// A recreated service never became healthy.
+    #[error("Service '{service}' failed its health check: {reason}")]
+    Health { service: String, reason: String },
+
+    /// The rollout failed and the previous version was restored.
+    #[error("{cause}; rolled back to '{tag}'")]
+    RolledBack { tag: String, cause: Box<Error> },
+
+    /// The rollout failed and restoring the previous version failed too.
+    #[error("{cause}; automatic rollback also failed: {rollback}")]
+    RollbackFailed {
+        cause: Box<Error>,
+        rollback: Box<Error>,
+    },
+
+    #[error("No previous version found in {clone_path} to roll back to")]
+    NoPreviousVersion { clone_path: String },
+
+    /// An offline artifact (bundle or tarball) is unreadable or malformed.
+    #[error("Artifact {}: {message}", path.display())]
+    Artifact { path: PathBuf, message: String },
+
+    /// The Traefik dynamic configuration of a version failed validation.
+    #[error("Invalid Traefik config in {}:{}", path.display(), problems.iter().map(|p| format!("\n  - {}", p)).collect::<String>())]
+    TraefikConfig {
+        path: PathBuf,
+        problems: Vec<String>,
+    },
+
+    /// A tag is unsigned or its signature is not trusted.
+    #[error("Refusing to deploy '{tag}': {reason}")]
+    Signature { tag: String, reason: String },
+
+    /// Another rollout holds the deploy lock for this clone path.
+    #[error("Deploy lock {} is held by {holder}; wait for it to finish or pass --break-lock if it is stale", path.display())]
+    Locked { path: PathBuf, holder: String },
+
+    /// The deployment history ledger could not be read or written.
+    #[error("History ledger {}: {message}", path.display())]
+    History { path: PathBuf, message: String },
+}
+
+impl Error {
+    /// Process exit code for this error:
+    ///
+    /// | code | meaning                                  |
+    /// |------|------------------------------------------|
+    /// | 1    | other failure (I/O, history, ...)        |
+    /// | 2    | configuration error                      |
+    /// | 3    | git error                                |
+    /// | 4    | docker error                             |
+    /// | 5    | health check failure                     |
+    /// | 6    | rollout failed and was rolled back       |
+    /// | 7    | rollout failed and the rollback failed   |
+    /// | 8    | another rollout holds the deploy lock    |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Config(_)
+            | Error::ComposeFile { .. }
+            | Error::Artifact { .. }
+            | Error::TraefikConfig { .. } => 2,
+            Error::Git { .. } | Error::Signature { .. } => 3,
+            Error::Command { .. }
+            | Error::DockerApi { .. }
+            | Error::DockerConnection { .. }
+            | Error::DockerTimeout { .. }
+            | Error::DockerResponse { .. }
+            | Error::NoContainers { .. } => 4,
+            Error::Health { .. } => 5,
+            Error::RolledBack { .. } => 6,
+            Error::RollbackFailed { .. } => 7,
+            Error::Locked { .. } => 8,
+            Error::Io { .. } | Error::NoPreviousVersion { .. } | Error::History { .. } => 1,
+        }
+    }
+
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn artifact(path: impl Into<PathBuf>, message: impl ToString) -> Self {
+        Error::Artifact {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn compose_file(path: impl Into<PathBuf>, message: impl ToString) -> Self {
+        Error::ComposeFile {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_error_display_includes_context() {
+        let err = Error::Git {
+            operation: "clone".to_string(),
+            tag: Some("v1.2.3".to_string()),
+            exit_code: Some(128),
+            stderr: "fatal: Remote branch v1.2.3 not found\n".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "git clone failed for tag 'v1.2.3' (exit code 128): fatal: Remote branch v1.2.3 not found"
+        );
+    }
+
+    #[test]
+    fn test_rolled_back_wraps_cause() {
+        let err = Error::RolledBack {
+            tag: "v1.0.0".to_string(),
+            cause: Box::new(Error::Health {
+                service: "traefik".to_string(),
+                reason: "container reported unhealthy".to_string(),
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Service 'traefik' failed its health check: container reported unhealthy; rolled back to 'v1.0.0'"
+        );
+    }
+}
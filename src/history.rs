@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::error::{Error, Result};
+
+/// Name of the append-only history ledger kept under the clone path.
+pub const HISTORY_FILE: &str = "deploy-history.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployMode {
+    Compose,
+    Swarm,
+    /// `current` switched under a watching Traefik, nothing recreated.
+    Reload,
+}
+
+impl std::fmt::Display for DeployMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeployMode::Compose => write!(f, "compose"),
+            DeployMode::Swarm => write!(f, "swarm"),
+            DeployMode::Reload => write!(f, "reload"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failed,
+    /// The rollout failed and the previous version was restored.
+    RolledBack,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::Failed => write!(f, "failed"),
+            Outcome::RolledBack => write!(f, "rolled_back"),
+        }
+    }
+}
+
+/// One deployment attempt, as recorded in the history ledger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub tag: String,
+    pub commit: Option<String>,
+    /// Who signed the deployed tag, when signature verification is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub mode: DeployMode,
+    #[serde(default)]
+    pub containers: Vec<String>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Starts a new entry for `tag`; it is marked failed until `finish` says otherwise.
+    pub fn start(tag: &str, mode: DeployMode) -> Self {
+        let now = Utc::now();
+        Self {
+            tag: tag.to_string(),
+            commit: None,
+            signer: None,
+            started_at: now,
+            finished_at: now,
+            mode,
+            containers: Vec::new(),
+            outcome: Outcome::Failed,
+            error: None,
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok(),
+        }
+    }
+
+    pub fn finish<T, E: std::fmt::Display>(&mut self, result: &std::result::Result<T, E>) {
+        self.finished_at = Utc::now();
+        match result {
+            Ok(_) => {
+                self.outcome = Outcome::Success;
+                self.error = None;
+            }
+            Err(e) => {
+                // Keep a rollback recorded during the attempt
+                if self.outcome != Outcome::RolledBack {
+                    self.outcome = Outcome::Failed;
+                }
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(clone_path: &str) -> Self {
+        Self {
+            path: Path::new(clone_path).join(HISTORY_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        let mut line = serde_json::to_string(entry).map_err(|e| Error::History {
+            path: self.path.clone(),
+            message: e.to_string(),
+        })?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| Error::io(&self.path, e))
+    }
+
+    /// Reads every entry in the ledger, oldest first. A missing ledger is empty.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io(&self.path, e)),
+        };
+        let mut entries = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!(
+                    "Skipping malformed history line {} in {}: {}",
+                    index + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Tags of successful deployments, most recent first.
+    pub fn successful_tags(&self) -> Result<Vec<String>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter(|entry| entry.outcome == Outcome::Success)
+            .map(|entry| entry.tag)
+            .collect())
+    }
+}
+
+/// Renders entries as a fixed-width table, one row per attempt.
+pub fn format_table(entries: &[HistoryEntry]) -> String {
+    let headers = [
+        "STARTED",
+        "TAG",
+        "COMMIT",
+        "MODE",
+        "OUTCOME",
+        "DURATION",
+        "USER",
+        "CONTAINERS",
+    ];
+    let rows: Vec<[String; 8]> = entries
+        .iter()
+        .map(|entry| {
+            let duration = entry.finished_at - entry.started_at;
+            [
+                entry.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                entry.tag.clone(),
+                entry
+                    .commit
+                    .as_deref()
+                    .map(|c| c.chars().take(12).collect())
+                    .unwrap_or_else(|| "-".to_string()),
+                entry.mode.to_string(),
+                entry.outcome.to_string(),
+                format!("{}s", duration.num_seconds()),
+                entry.user.clone().unwrap_or_else(|| "-".to_string()),
+                if entry.containers.is_empty() {
+                    "-".to_string()
+                } else {
+                    entry.containers.join(",")
+                },
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_row = |cells: &[&str]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    };
+    push_row(&headers);
+    for row in &rows {
+        let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+        push_row(&cells);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_and_read_entries() {
+        let dir = tempdir().unwrap();
+        let history = History::new(dir.path().to_str().unwrap());
+
+        let mut first = HistoryEntry::start("v1.0.0", DeployMode::Compose);
+        first.containers.push("traefik".to_string());
+        first.finish::<(), String>(&Ok(()));
+        history.append(&first).unwrap();
+
+        let mut second = HistoryEntry::start("v1.1.0", DeployMode::Swarm);
+        second.finish::<(), String>(&Err("docker service update failed".to_string()));
+        history.append(&second).unwrap();
+
+        let entries = history.entries().unwrap();
+        assert_eq!(entries, vec![first, second]);
+        assert_eq!(
+            entries[1].error.as_deref(),
+            Some("docker service update failed")
+        );
+        assert_eq!(history.successful_tags().unwrap(), vec!["v1.0.0"]);
+    }
+
+    #[test]
+    fn test_finish_keeps_rolled_back_outcome() {
+        let mut entry = HistoryEntry::start("v2.0.0", DeployMode::Compose);
+        entry.outcome = Outcome::RolledBack;
+        entry.finish::<(), String>(&Err("traefik reported unhealthy".to_string()));
+        assert_eq!(entry.outcome, Outcome::RolledBack);
+    }
+
+    #[test]
+    fn test_missing_ledger_is_empty() {
+        let dir = tempdir().unwrap();
+        let history = History::new(dir.path().to_str().unwrap());
+        assert!(history.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_format_table() {
+        let mut entry = HistoryEntry::start("v1.0.0", DeployMode::Compose);
+        entry.commit = Some("0123456789abcdef0123".to_string());
+        entry.finish::<(), String>(&Ok(()));
+        let table = format_table(&[entry]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("STARTED"));
+        assert!(lines[1].contains("v1.0.0"));
+        assert!(lines[1].contains("0123456789ab "));
+        assert!(lines[1].contains("success"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Minimal HTTP/1.1 request/response handling for talking to local APIs
+//! (the Docker Engine socket) without pulling in a full HTTP stack.
+
+use std::fmt;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+#[derive(Debug)]
+pub enum HttpError {
+    Io(std::io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Io(e) => write!(f, "HTTP I/O error: {}", e),
+            HttpError::Malformed(msg) => write!(f, "Malformed HTTP response: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Io(e) => Some(e),
+            HttpError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(e: std::io::Error) -> Self {
+        HttpError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Case-insensitive lookup of the first header named `name`.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Serializes a request. `Connection: close` is always sent so the server ends the stream.
+pub fn encode_request(
+    method: &str,
+    path: &str,
+    host: &str,
+    body: Option<(&str, &[u8])>,
+) -> Vec<u8> {
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, host);
+    match body {
+        Some((content_type, body)) => {
+            request.push_str(&format!(
+                "Content-Type: {}\r\nContent-Length: {}\r\n",
+                content_type,
+                body.len()
+            ));
+            request.push_str("Connection: close\r\n\r\n");
+            let mut bytes = request.into_bytes();
+            bytes.extend_from_slice(body);
+            bytes
+        }
+        None => {
+            if method == "POST" || method == "PUT" {
+                request.push_str("Content-Length: 0\r\n");
+            }
+            request.push_str("Connection: close\r\n\r\n");
+            request.into_bytes()
+        }
+    }
+}
+
+/// Reads a full response, decoding `Transfer-Encoding: chunked` and honouring
+/// `Content-Length`. `method` is needed because HEAD responses carry no body.
+pub async fn read_response<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    method: &str,
+) -> Result<Response, HttpError> {
+    let status_line = read_line(reader)
+        .await?
+        .ok_or_else(|| HttpError::Malformed("connection closed before status line".into()))?;
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or_default();
+    if !version.starts_with("HTTP/1.") {
+        return Err(HttpError::Malformed(format!(
+            "unexpected status line '{}'",
+            status_line
+        )));
+    }
+    let status = parts
+        .next()
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| HttpError::Malformed(format!("invalid status line '{}'", status_line)))?;
+    let reason = parts.next().unwrap_or_default().to_string();
+
+    let headers = read_headers(reader).await?;
+    let mut response = Response {
+        status,
+        reason,
+        headers,
+        body: Vec::new(),
+    };
+
+    // 1xx, 204, 304 and HEAD responses never carry a body
+    if method == "HEAD" || (100..200).contains(&status) || status == 204 || status == 304 {
+        return Ok(response);
+    }
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .map(|te| te.to_ascii_lowercase().contains("chunked"))
+        .unwrap_or(false);
+    response.body = if chunked {
+        read_chunked_body(reader).await?
+    } else if let Some(length) = response.header("Content-Length") {
+        let length: usize = length
+            .trim()
+            .parse()
+            .map_err(|_| HttpError::Malformed(format!("invalid Content-Length '{}'", length)))?;
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        body
+    } else {
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).await?;
+        body
+    };
+    Ok(response)
+}
+
+async fn read_headers<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Vec<(String, String)>, HttpError> {
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)
+            .await?
+            .ok_or_else(|| HttpError::Malformed("connection closed inside headers".into()))?;
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| HttpError::Malformed(format!("invalid header line '{}'", line)))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+}
+
+async fn read_chunked_body<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        let size_line = read_line(reader)
+            .await?
+            .ok_or_else(|| HttpError::Malformed("connection closed before chunk size".into()))?;
+        // Ignore chunk extensions (`<size>;name=value`)
+        let size_str = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_str, 16)
+            .map_err(|_| HttpError::Malformed(format!("invalid chunk size '{}'", size_line)))?;
+        if size == 0 {
+            // Skip optional trailer headers up to the final empty line
+            read_headers(reader).await?;
+            return Ok(body);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+        let terminator = read_line(reader).await?;
+        if terminator.as_deref() != Some("") {
+            return Err(HttpError::Malformed("chunk not terminated by CRLF".into()));
+        }
+    }
+}
+
+/// Reads one CRLF- (or LF-) terminated line. Returns `None` at end of stream.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<String>, HttpError> {
+    let mut buf = Vec::new();
+    if reader.read_until(b'\n', &mut buf).await? == 0 {
+        return Ok(None);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+    String::from_utf8(buf)
+        .map(Some)
+        .map_err(|_| HttpError::Malformed("non UTF-8 header line".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    async fn parse(raw: &[u8]) -> Result<Response, HttpError> {
+        read_response(&mut Cursor::new(raw.to_vec()), "GET").await
+    }
+
+    #[tokio::test]
+    async fn test_decodes_multiple_chunks() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Type: application/json\r\n\r\n5\r\n[{\"a\"\r\n4;ext=1\r\n:1},\r\nA\r\n{\"b\":2}]  \r\n0\r\nX-Trailer: yes\r\n\r\n";
+        let response = parse(raw).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.text(), "[{\"a\":1},{\"b\":2}]  ");
+        assert_eq!(response.header("content-type"), Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_reads_content_length_and_binary_body() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[1, 0, 0, 0, 0xff, b'\n']);
+        raw.extend_from_slice(b"ignored");
+        let response = parse(&raw).await.unwrap();
+        assert_eq!(response.body, vec![1, 0, 0, 0, 0xff, b'\n']);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_parsed() {
+        let raw = b"HTTP/1.1 404 Not Found\r\nContent-Length: 29\r\n\r\n{\"message\":\"No such thing\"}\r\n";
+        let response = parse(raw).await.unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.reason, "Not Found");
+        assert!(!response.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_no_body_for_no_content() {
+        let raw = b"HTTP/1.1 204 No Content\r\n\r\n";
+        let response = parse(raw).await.unwrap();
+        assert!(response.is_success());
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_body_until_eof_without_length() {
+        let raw = b"HTTP/1.0 200 OK\r\n\r\nhello";
+        assert_eq!(parse(raw).await.unwrap().text(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_chunk_is_an_error() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nshort";
+        assert!(parse(raw).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_status_line() {
+        assert!(parse(b"garbage\r\n\r\n").await.is_err());
+    }
+
+    #[test]
+    fn test_encode_post_request() {
+        let request = encode_request("POST", "/containers/abc/stop", "localhost", None);
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST /containers/abc/stop HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Length: 0\r\n"));
+        assert!(request.ends_with("Connection: close\r\n\r\n"));
+    }
+}
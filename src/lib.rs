@@ -1,15 +1,33 @@
+pub mod artifact;
 pub mod cli;
+pub mod compose_edit;
 pub mod config;
 pub mod deployment_manager;
 pub mod docker_client;
+pub mod error;
 pub mod git_client;
+pub mod history;
+pub mod http;
+pub mod lock;
+pub mod plan;
+pub mod project_file;
+pub mod route_diff;
+pub mod status;
+pub mod traefik;
+pub mod traefik_api;
 pub mod types;
 
 use clap::Parser;
-use cli::deploy as _deploy;
+use cli::execute as _execute;
 pub use cli::CLI;
+pub use error::{Error, Result};
+use std::process::ExitCode;
 
-pub async fn run() {
+/// Parses the command line, runs it and maps the outcome to a process exit code.
+pub async fn run() -> ExitCode {
     let cli = CLI::parse();
-    _deploy(cli).await;
+    match _execute(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => ExitCode::from(e.exit_code()),
+    }
 }
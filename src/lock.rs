@@ -0,0 +1,243 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::error::{Error, Result};
+
+/// Name of the lock file kept next to the versioned config directories.
+pub const LOCK_FILE: &str = ".rolling-deployer.lock";
+
+/// How often a busy lock is retried while waiting for it.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Who holds the deploy lock, as written into the lock file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub tag: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} deploying '{}' since {}",
+            self.pid,
+            self.tag,
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )?;
+        if let Some(user) = &self.user {
+            write!(f, " (user {})", user)?;
+        }
+        Ok(())
+    }
+}
+
+/// Exclusive `flock` on `clone_path/.rolling-deployer.lock`, held for a whole rollout.
+/// The lock is released when this value is dropped or the process exits.
+#[derive(Debug)]
+pub struct DeployLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl DeployLock {
+    /// Takes the lock for deploying `tag`, retrying for up to `wait` while another
+    /// process holds it. With `break_lock`, a lock still held after `wait` is taken
+    /// over by replacing the lock file.
+    pub async fn acquire(
+        clone_path: &str,
+        tag: &str,
+        wait: Duration,
+        break_lock: bool,
+    ) -> Result<Self> {
+        let started = Instant::now();
+        let mut logged = false;
+        loop {
+            if let Some(lock) = Self::try_acquire(clone_path, tag)? {
+                return Ok(lock);
+            }
+
+            let path = Path::new(clone_path).join(LOCK_FILE);
+            let holder = Self::describe(Self::read_holder(&path).as_ref());
+            if started.elapsed() >= wait {
+                if !break_lock {
+                    return Err(Error::Locked { path, holder });
+                }
+                warn!("Breaking deploy lock {} held by {}", path.display(), holder);
+                // The old holder keeps its lock on the unlinked file; new runs
+                // contend on the fresh one
+                std::fs::remove_file(&path).map_err(|e| Error::io(&path, e))?;
+                return Self::try_acquire(clone_path, tag)?.ok_or_else(|| Error::Locked {
+                    holder: Self::describe(Self::read_holder(&path).as_ref()),
+                    path,
+                });
+            }
+            if !logged {
+                info!(
+                    "Waiting up to {}s for deploy lock {} held by {}",
+                    wait.as_secs(),
+                    path.display(),
+                    holder
+                );
+                logged = true;
+            }
+            tokio::time::sleep(LOCK_POLL_INTERVAL.min(wait.saturating_sub(started.elapsed())))
+                .await;
+        }
+    }
+
+    /// Takes the lock only if it is free right now.
+    pub fn try_acquire(clone_path: &str, tag: &str) -> Result<Option<Self>> {
+        std::fs::create_dir_all(clone_path).map_err(|e| Error::io(clone_path, e))?;
+        let path = Path::new(clone_path).join(LOCK_FILE);
+        loop {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(|e| Error::io(&path, e))?;
+            match file.try_lock() {
+                // A lock file broken with `--break-lock` after we opened it can still be
+                // locked, but no one else contends on it any more; start over with the
+                // file now at `path`
+                Ok(()) if !Self::is_current_file(&file, &path)? => continue,
+                Ok(()) => return Self::locked(file, path, tag).map(Some),
+                Err(TryLockError::WouldBlock) => return Ok(None),
+                Err(TryLockError::Error(e)) => return Err(Error::io(&path, e)),
+            }
+        }
+    }
+
+    /// Whether `file` is still the one linked at `path`.
+    fn is_current_file(file: &File, path: &Path) -> Result<bool> {
+        let open = file.metadata().map_err(|e| Error::io(path, e))?;
+        match std::fs::metadata(path) {
+            Ok(linked) => Ok(open.dev() == linked.dev() && open.ino() == linked.ino()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(Error::io(path, e)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the new holder in the freshly locked file.
+    fn locked(mut file: File, path: PathBuf, tag: &str) -> Result<Self> {
+        let holder = LockHolder {
+            pid: std::process::id(),
+            tag: tag.to_string(),
+            started_at: Utc::now(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok(),
+        };
+        let content = serde_json::to_string(&holder).unwrap_or_default();
+        file.set_len(0)
+            .and_then(|()| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| file.write_all(content.as_bytes()))
+            .and_then(|()| file.flush())
+            .map_err(|e| Error::io(&path, e))?;
+        Ok(Self { file, path })
+    }
+
+    /// Reads the holder recorded in the lock file, if it is readable.
+    pub fn read_holder(path: &Path) -> Option<LockHolder> {
+        let mut content = String::new();
+        File::open(path).ok()?.read_to_string(&mut content).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn describe(holder: Option<&LockHolder>) -> String {
+        holder
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "an unknown process".to_string())
+    }
+}
+
+impl Drop for DeployLock {
+    fn drop(&mut self) {
+        // Clear the holder so a stale read doesn't name a finished run
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_second_lock_names_holder() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+
+        let lock = DeployLock::acquire(base, "v1.0.0", Duration::ZERO, false)
+            .await
+            .unwrap();
+        let holder = DeployLock::read_holder(lock.path()).unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        assert_eq!(holder.tag, "v1.0.0");
+
+        let err = DeployLock::acquire(base, "v1.1.0", Duration::from_millis(300), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 8);
+        assert!(err.to_string().contains("deploying 'v1.0.0'"), "{}", err);
+        assert!(DeployLock::try_acquire(base, "v1.1.0").unwrap().is_none());
+
+        drop(lock);
+        assert!(DeployLock::try_acquire(base, "v1.1.0").unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_break_lock_takes_over_held_lock() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+
+        let _stale = DeployLock::acquire(base, "v1.0.0", Duration::ZERO, false)
+            .await
+            .unwrap();
+        let lock = DeployLock::acquire(base, "v1.1.0", Duration::ZERO, true)
+            .await
+            .unwrap();
+        assert_eq!(DeployLock::read_holder(lock.path()).unwrap().tag, "v1.1.0");
+    }
+
+    #[tokio::test]
+    async fn test_lock_on_broken_lock_file_is_not_current() {
+        let dir = tempdir().unwrap();
+        let base = dir.path().to_str().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+
+        // Opened before `--break-lock` replaced the file, locked after the old holder quit
+        let stale = DeployLock::acquire(base, "v1.0.0", Duration::ZERO, false)
+            .await
+            .unwrap();
+        let late = File::open(&path).unwrap();
+        assert!(DeployLock::is_current_file(&late, &path).unwrap());
+        let lock = DeployLock::acquire(base, "v1.1.0", Duration::ZERO, true)
+            .await
+            .unwrap();
+        drop(stale);
+        late.try_lock().unwrap();
+        assert!(!DeployLock::is_current_file(&late, &path).unwrap());
+
+        // The replacement is still held, so a new run has to wait for it
+        assert!(DeployLock::try_acquire(base, "v1.2.0").unwrap().is_none());
+        drop(lock);
+        assert!(DeployLock::try_acquire(base, "v1.2.0").unwrap().is_some());
+    }
+}
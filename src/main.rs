@@ -1,6 +1,7 @@
 use rolling_deployer::run;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
-    run().await;
+async fn main() -> ExitCode {
+    run().await
 }
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Everything a deploy would change, computed without side effects (`--dry-run`).
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployPlan {
+    pub project: String,
+    pub tag: String,
+    /// The reference the tag was resolved from (branch, SHA, semver requirement, ...).
+    pub requested: String,
+    /// The `git` (or `tar`) commands that would fetch the version; empty if it is
+    /// already checked out.
+    pub fetch_commands: Vec<Vec<String>>,
+    pub versioned_path: String,
+    /// Unified diff of the compose file, or `None` if it would not change.
+    pub compose_diff: Option<String>,
+    pub symlink: SymlinkChange,
+    pub recreate: Vec<PlannedRecreate>,
+    /// `docker service update` command that would run in Swarm mode.
+    pub swarm_command: Option<Vec<String>>,
+    /// Reload strategy: nothing is restarted, Traefik picks up `current` itself.
+    pub reload: bool,
+    /// Old versioned config directories that would be deleted.
+    pub cleanup: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymlinkChange {
+    pub path: String,
+    pub from: Option<String>,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedRecreate {
+    pub container: String,
+    pub service: String,
+}
+
+impl fmt::Display for DeployPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Plan for project '{}' at tag '{}' (dry run, nothing was changed)",
+            self.project, self.tag
+        )?;
+
+        writeln!(f, "\n1. Fetch config")?;
+        if self.requested != self.tag {
+            writeln!(f, "   resolved '{}' to '{}'", self.requested, self.tag)?;
+        }
+        if self.fetch_commands.is_empty() {
+            writeln!(f, "   using existing config at {}", self.versioned_path)?;
+        }
+        for args in &self.fetch_commands {
+            writeln!(f, "   {}", args.join(" "))?;
+        }
+
+        writeln!(f, "\n2. Update compose file")?;
+        match &self.compose_diff {
+            Some(diff) => {
+                for line in diff.lines() {
+                    writeln!(f, "   {}", line)?;
+                }
+            }
+            None => writeln!(f, "   no changes")?,
+        }
+
+        writeln!(f, "\n3. Switch symlink")?;
+        writeln!(
+            f,
+            "   {}: {} -> {}",
+            self.symlink.path,
+            self.symlink.from.as_deref().unwrap_or("<none>"),
+            self.symlink.to
+        )?;
+
+        writeln!(f, "\n4. Restart")?;
+        if self.reload {
+            writeln!(
+                f,
+                "   nothing restarted; Traefik reloads from the switched symlink"
+            )?;
+        } else if let Some(args) = &self.swarm_command {
+            writeln!(f, "   {}", args.join(" "))?;
+        } else if self.recreate.is_empty() {
+            writeln!(f, "   no running containers found")?;
+        } else {
+            for planned in &self.recreate {
+                writeln!(
+                    f,
+                    "   recreate service '{}' (container {})",
+                    planned.service, planned.container
+                )?;
+            }
+        }
+
+        writeln!(f, "\n5. Clean up old configs")?;
+        if self.cleanup.is_empty() {
+            writeln!(f, "   nothing to remove")?;
+        } else {
+            for dir in &self.cleanup {
+                writeln!(f, "   remove {}", dir)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+//! Declarative multi-project configuration (`rolling-deployer.yaml` or `.toml`).
+//!
+//! ```yaml
+//! defaults:
+//!   clone_path: /opt/configs
+//!   socket_path: /var/run/docker.sock
+//! projects:
+//!   - name: edge
+//!     repo_url: https://github.com/org/edge-proxy.git
+//!     mount_path: /etc/traefik/dynamic
+//!     compose_file: [/srv/edge/docker-compose.yml, /srv/edge/docker-compose.prod.yml]
+//!     compose_profiles: [edge]
+//!     services: [traefik]
+//!   - name: internal
+//!     repo_url: https://github.com/org/internal-proxy.git
+//!     clone_path: /opt/configs-internal
+//!     mount_path: /etc/traefik/dynamic
+//!     strategy: swarm
+//! ```
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::Strategy;
+use crate::error::{Error, Result};
+
+/// File names looked up in the working directory when `--config` is not given.
+pub const DEFAULT_PROJECT_FILES: [&str; 3] = [
+    "rolling-deployer.yaml",
+    "rolling-deployer.yml",
+    "rolling-deployer.toml",
+];
+
+/// Settings for one project. Every field is optional so the same shape
+/// works for the shared `defaults` block.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSettings {
+    pub name: Option<String>,
+    pub repo_url: Option<String>,
+    pub clone_path: Option<String>,
+    pub mount_path: Option<String>,
+    /// One compose file, or a list of them in merge order.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub compose_file: Option<Vec<String>>,
+    pub compose_profiles: Option<Vec<String>>,
+    pub compose_project: Option<String>,
+    pub socket_path: Option<String>,
+    pub services: Option<Vec<String>>,
+    pub strategy: Option<Strategy>,
+    pub health_timeout: Option<u64>,
+    pub health_grace_period: Option<u64>,
+    pub lock_timeout: Option<u64>,
+    pub config_subpath: Option<String>,
+    pub verify_tags: Option<bool>,
+    pub gpg_home: Option<String>,
+    pub allowed_signers: Option<String>,
+    pub ssh_key: Option<String>,
+    pub known_hosts: Option<String>,
+    pub git_token_file: Option<String>,
+    pub git_token_user: Option<String>,
+    pub git_credential_helper: Option<String>,
+    pub git_non_interactive: Option<bool>,
+    pub traefik_api: Option<String>,
+    /// Image substring of the containers to roll; `name` only identifies the project.
+    pub image: Option<String>,
+}
+
+impl ProjectSettings {
+    /// Fills every unset field from `defaults`.
+    fn with_defaults(self, defaults: &ProjectSettings) -> Self {
+        Self {
+            name: self.name.or_else(|| defaults.name.clone()),
+            repo_url: self.repo_url.or_else(|| defaults.repo_url.clone()),
+            clone_path: self.clone_path.or_else(|| defaults.clone_path.clone()),
+            mount_path: self.mount_path.or_else(|| defaults.mount_path.clone()),
+            compose_file: self.compose_file.or_else(|| defaults.compose_file.clone()),
+            compose_profiles: self
+                .compose_profiles
+                .or_else(|| defaults.compose_profiles.clone()),
+            compose_project: self
+                .compose_project
+                .or_else(|| defaults.compose_project.clone()),
+            socket_path: self.socket_path.or_else(|| defaults.socket_path.clone()),
+            services: self.services.or_else(|| defaults.services.clone()),
+            strategy: self.strategy.or(defaults.strategy),
+            health_timeout: self.health_timeout.or(defaults.health_timeout),
+            health_grace_period: self.health_grace_period.or(defaults.health_grace_period),
+            lock_timeout: self.lock_timeout.or(defaults.lock_timeout),
+            config_subpath: self
+                .config_subpath
+                .or_else(|| defaults.config_subpath.clone()),
+            verify_tags: self.verify_tags.or(defaults.verify_tags),
+            gpg_home: self.gpg_home.or_else(|| defaults.gpg_home.clone()),
+            allowed_signers: self
+                .allowed_signers
+                .or_else(|| defaults.allowed_signers.clone()),
+            ssh_key: self.ssh_key.or_else(|| defaults.ssh_key.clone()),
+            known_hosts: self.known_hosts.or_else(|| defaults.known_hosts.clone()),
+            git_token_file: self
+                .git_token_file
+                .or_else(|| defaults.git_token_file.clone()),
+            git_token_user: self
+                .git_token_user
+                .or_else(|| defaults.git_token_user.clone()),
+            git_credential_helper: self
+                .git_credential_helper
+                .or_else(|| defaults.git_credential_helper.clone()),
+            git_non_interactive: self.git_non_interactive.or(defaults.git_non_interactive),
+            traefik_api: self.traefik_api.or_else(|| defaults.traefik_api.clone()),
+            image: self.image.or_else(|| defaults.image.clone()),
+        }
+    }
+}
+
+/// Reads a setting given either as a single string or as a list of strings.
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(
+        Option::<OneOrMany>::deserialize(deserializer)?.map(|value| match value {
+            OneOrMany::One(one) => vec![one],
+            OneOrMany::Many(many) => many,
+        }),
+    )
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectFile {
+    #[serde(default)]
+    pub defaults: ProjectSettings,
+    #[serde(default)]
+    pub projects: Vec<ProjectSettings>,
+}
+
+impl ProjectFile {
+    /// Parses a project file; `.toml` files are read as TOML, anything else as YAML.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let invalid = |message: String| {
+            Error::Config(format!(
+                "Invalid project file {}: {}",
+                path.display(),
+                message
+            ))
+        };
+        let file: ProjectFile = if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+            toml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+        };
+
+        for (index, project) in file.projects.iter().enumerate() {
+            let name = project
+                .name
+                .as_deref()
+                .ok_or_else(|| invalid(format!("project #{} has no name", index + 1)))?;
+            if file.projects[..index]
+                .iter()
+                .any(|other| other.name.as_deref() == Some(name))
+            {
+                return Err(invalid(format!("project '{}' is defined twice", name)));
+            }
+        }
+
+        // Projects sharing a clone path would share `current`, the mirror, the history,
+        // the deploy lock, and delete each other's config directories on cleanup
+        let clone_path = |project: &ProjectSettings| {
+            project
+                .clone_path
+                .clone()
+                .or_else(|| file.defaults.clone_path.clone())
+                .map(|path| path.trim_end_matches('/').to_string())
+        };
+        for (index, project) in file.projects.iter().enumerate() {
+            let path = clone_path(project);
+            if let Some(other) = file.projects[..index]
+                .iter()
+                .find(|other| clone_path(other) == path)
+            {
+                return Err(invalid(format!(
+                    "projects '{}' and '{}' both use clone_path {}; give each project its own",
+                    other.name.as_deref().unwrap_or_default(),
+                    project.name.as_deref().unwrap_or_default(),
+                    path.as_deref().unwrap_or("from --clone-path or CLONE_PATH")
+                )));
+            }
+        }
+        Ok(file)
+    }
+
+    /// Finds the project file to use: `explicit` if given (it must exist),
+    /// otherwise the first of `DEFAULT_PROJECT_FILES` present in the working directory.
+    pub fn locate(explicit: Option<&str>) -> Result<Option<PathBuf>> {
+        match explicit {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if path.exists() {
+                    Ok(Some(path))
+                } else {
+                    Err(Error::Config(format!(
+                        "Project file {} does not exist",
+                        path.display()
+                    )))
+                }
+            }
+            None => Ok(DEFAULT_PROJECT_FILES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.projects
+            .iter()
+            .filter_map(|p| p.name.as_deref())
+            .collect()
+    }
+
+    /// Picks the projects to act on, in file order, each merged with `defaults`.
+    /// Without `project` or `all` the file must define exactly one project.
+    pub fn select(&self, project: Option<&str>, all: bool) -> Result<Vec<ProjectSettings>> {
+        let selected: Vec<&ProjectSettings> = match (project, all) {
+            (_, true) => self.projects.iter().collect(),
+            (Some(name), false) => {
+                let found = self
+                    .projects
+                    .iter()
+                    .find(|p| p.name.as_deref() == Some(name))
+                    .ok_or_else(|| {
+                        Error::Config(format!(
+                            "Unknown project '{}'. Known projects: {}",
+                            name,
+                            self.names().join(", ")
+                        ))
+                    })?;
+                vec![found]
+            }
+            (None, false) if self.projects.len() == 1 => self.projects.iter().collect(),
+            (None, false) => {
+                return Err(Error::Config(format!(
+                    "Project file defines {} projects; pick one with --project <name> or use --all. Known projects: {}",
+                    self.projects.len(),
+                    self.names().join(", ")
+                )))
+            }
+        };
+        if selected.is_empty() {
+            return Err(Error::Config(
+                "Project file defines no projects".to_string(),
+            ));
+        }
+        Ok(selected
+            .into_iter()
+            .map(|p| p.clone().with_defaults(&self.defaults))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const YAML: &str = r#"
+defaults:
+  clone_path: /opt/configs
+  mount_path: /etc/traefik/dynamic
+  image: traefik
+projects:
+  - name: edge
+    repo_url: https://example.com/edge.git
+    compose_file: [docker-compose.yml, docker-compose.prod.yml]
+    compose_profiles: [edge]
+    services: [traefik]
+  - name: internal
+    compose_file: /srv/internal/compose.yml
+    repo_url: https://example.com/internal.git
+    clone_path: /opt/internal
+    image: registry.example.com/traefik-internal
+    strategy: swarm
+"#;
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_select_merges_defaults() {
+        let dir = tempdir().unwrap();
+        let file = ProjectFile::load(&write(dir.path(), "rolling-deployer.yaml", YAML)).unwrap();
+
+        let internal = file.select(Some("internal"), false).unwrap();
+        assert_eq!(internal.len(), 1);
+        assert_eq!(internal[0].clone_path.as_deref(), Some("/opt/internal"));
+        assert_eq!(
+            internal[0].mount_path.as_deref(),
+            Some("/etc/traefik/dynamic")
+        );
+        assert_eq!(internal[0].strategy, Some(Strategy::Swarm));
+        assert_eq!(
+            internal[0].image.as_deref(),
+            Some("registry.example.com/traefik-internal")
+        );
+        assert_eq!(
+            internal[0].compose_file,
+            Some(vec!["/srv/internal/compose.yml".to_string()])
+        );
+
+        let all = file.select(None, true).unwrap();
+        let names: Vec<_> = all.iter().map(|p| p.name.as_deref().unwrap()).collect();
+        assert_eq!(names, vec!["edge", "internal"]);
+        assert_eq!(all[0].clone_path.as_deref(), Some("/opt/configs"));
+        assert_eq!(all[0].image.as_deref(), Some("traefik"));
+        assert_eq!(all[0].services, Some(vec!["traefik".to_string()]));
+        assert_eq!(
+            all[0].compose_file.as_deref(),
+            Some(
+                &[
+                    "docker-compose.yml".to_string(),
+                    "docker-compose.prod.yml".to_string()
+                ][..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_select_requires_choice_with_several_projects() {
+        let dir = tempdir().unwrap();
+        let file = ProjectFile::load(&write(dir.path(), "rolling-deployer.yaml", YAML)).unwrap();
+        assert!(matches!(file.select(None, false), Err(Error::Config(_))));
+        assert!(matches!(
+            file.select(Some("missing"), false),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_toml() {
+        let dir = tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "rolling-deployer.toml",
+            r#"
+[defaults]
+clone_path = "/opt/configs"
+
+[[projects]]
+name = "edge"
+repo_url = "https://example.com/edge.git"
+mount_path = "/etc/traefik/dynamic"
+strategy = "rolling"
+"#,
+        );
+        let file = ProjectFile::load(&path).unwrap();
+        let selected = file.select(None, false).unwrap();
+        assert_eq!(selected[0].name.as_deref(), Some("edge"));
+        assert_eq!(selected[0].clone_path.as_deref(), Some("/opt/configs"));
+        assert_eq!(selected[0].strategy, Some(Strategy::Rolling));
+    }
+
+    #[test]
+    fn test_load_rejects_duplicate_and_unnamed_projects() {
+        let dir = tempdir().unwrap();
+        let duplicate = write(
+            dir.path(),
+            "dup.yaml",
+            "projects:\n  - name: a\n  - name: a\n",
+        );
+        assert!(ProjectFile::load(&duplicate).is_err());
+        let unnamed = write(dir.path(), "unnamed.yaml", "projects:\n  - repo_url: x\n");
+        assert!(ProjectFile::load(&unnamed).is_err());
+        let shared = write(
+            dir.path(),
+            "shared.yaml",
+            "defaults:\n  clone_path: /opt/configs\nprojects:\n  - name: a\n  - name: b\n    clone_path: /opt/configs/\n",
+        );
+        let err = ProjectFile::load(&shared).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("projects 'a' and 'b' both use clone_path /opt/configs"),
+            "{}",
+            err
+        );
+    }
+}
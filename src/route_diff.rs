@@ -0,0 +1,299 @@
+use serde::Serialize;
+use serde_yaml::Value;
+use std::fmt;
+
+use crate::traefik::{describe, DynamicConfig};
+
+/// Definition kinds compared by a route diff, as `(protocol, section)`.
+const COMPARED: &[(&str, &str)] = &[
+    ("http", "routers"),
+    ("http", "services"),
+    ("http", "middlewares"),
+    ("tcp", "routers"),
+    ("tcp", "services"),
+    ("tcp", "middlewares"),
+    ("udp", "routers"),
+    ("udp", "services"),
+    ("tls", "options"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One router, service, middleware or TLS option that differs between two versions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteChange {
+    pub change: ChangeKind,
+    pub protocol: String,
+    pub section: String,
+    pub name: String,
+    /// Settings only in the old version, flattened to `path: value` lines such as
+    /// `rule: Host(`a.com`)` or `loadBalancer.servers[].url: http://10.0.0.1`.
+    pub removed: Vec<String>,
+    /// Settings only in the new version, in the same form.
+    pub added: Vec<String>,
+}
+
+/// The routing changes between two config versions (`rolling-deployer diff`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteDiff {
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<RouteChange>,
+}
+
+impl RouteDiff {
+    pub fn between(from: &str, old: &DynamicConfig, to: &str, new: &DynamicConfig) -> Self {
+        let mut keys: Vec<_> = old
+            .definitions
+            .keys()
+            .chain(new.definitions.keys())
+            .filter(|(protocol, section, _)| {
+                COMPARED.contains(&(protocol.as_str(), section.as_str()))
+            })
+            .collect();
+        keys.sort_by_key(|(protocol, section, name)| {
+            let kind = COMPARED
+                .iter()
+                .position(|&(p, s)| p == protocol && s == section);
+            (kind, name)
+        });
+        keys.dedup();
+
+        let changes = keys
+            .into_iter()
+            .filter_map(|key| {
+                let old = old.definitions.get(key).map(|d| flatten(&d.value));
+                let new = new.definitions.get(key).map(|d| flatten(&d.value));
+                let (change, removed, added) = match (old, new) {
+                    (None, Some(new)) => (ChangeKind::Added, Vec::new(), new),
+                    (Some(old), None) => (ChangeKind::Removed, old, Vec::new()),
+                    (Some(old), Some(new)) if old != new => {
+                        let (removed, added) = line_changes(&old, &new);
+                        (ChangeKind::Changed, removed, added)
+                    }
+                    _ => return None,
+                };
+                let (protocol, section, name) = key.clone();
+                Some(RouteChange {
+                    change,
+                    protocol,
+                    section,
+                    name,
+                    removed,
+                    added,
+                })
+            })
+            .collect();
+
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            changes,
+        }
+    }
+}
+
+impl fmt::Display for RouteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No routing changes from {} to {}", self.from, self.to);
+        }
+        writeln!(f, "Routing changes from {} to {}:", self.from, self.to)?;
+        for change in &self.changes {
+            let marker = match change.change {
+                ChangeKind::Added => '+',
+                ChangeKind::Removed => '-',
+                ChangeKind::Changed => '~',
+            };
+            writeln!(
+                f,
+                "\n{} {} '{}'",
+                marker,
+                describe(&change.protocol, &change.section),
+                change.name
+            )?;
+            for line in &change.removed {
+                writeln!(f, "    - {}", line)?;
+            }
+            for line in &change.added {
+                writeln!(f, "    + {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lines of `old` and `new` that the other lacks, in order.
+fn line_changes(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let old: Vec<&str> = old.iter().map(String::as_str).collect();
+    let new: Vec<&str> = new.iter().map(String::as_str).collect();
+    let diff = similar::TextDiff::from_slices(&old, &new);
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Delete => removed.push(change.value().to_string()),
+            similar::ChangeTag::Insert => added.push(change.value().to_string()),
+            similar::ChangeTag::Equal => {}
+        }
+    }
+    (removed, added)
+}
+
+/// Renders a definition as sorted `path: value` lines so that key order and
+/// formatting don't show up as changes.
+fn flatten(value: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    flatten_into(value, "", &mut lines);
+    lines
+}
+
+fn flatten_into(value: &Value, path: &str, lines: &mut Vec<String>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            let mut fields: Vec<_> = mapping.iter().collect();
+            fields.sort_by_key(|(key, _)| scalar(key).to_ascii_lowercase());
+            for (key, value) in fields {
+                let key = scalar(key);
+                let path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten_into(value, &path, lines);
+            }
+        }
+        Value::Sequence(items) if !items.iter().all(is_scalar) => {
+            for item in items {
+                flatten_into(item, &format!("{}[]", path), lines);
+            }
+        }
+        Value::Sequence(items) => {
+            let items: Vec<_> = items.iter().map(scalar).collect();
+            lines.push(format!("{}: [{}]", path, items.join(", ")));
+        }
+        Value::Tagged(tagged) => flatten_into(&tagged.value, path, lines),
+        other => lines.push(format!("{}: {}", path, scalar(other))),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(
+        value,
+        Value::Mapping(_) | Value::Sequence(_) | Value::Tagged(_)
+    )
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Mapping(m) if m.is_empty() => "{}".to_string(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn config(content: &str) -> DynamicConfig {
+        let mut config = DynamicConfig::default();
+        config.add_file(Path::new("dynamic.yml"), content);
+        assert!(config.problems.is_empty(), "{:?}", config.problems);
+        config
+    }
+
+    #[test]
+    fn test_route_diff_between_versions() {
+        let old = config(
+            r#"
+http:
+  routers:
+    api:
+      rule: "Host(`api.example.com`)"
+      entryPoints: [web]
+      service: api
+    legacy:
+      rule: "Host(`old.example.com`)"
+      service: api
+  services:
+    api:
+      loadBalancer:
+        servers:
+          - url: http://10.0.0.1:8080
+          - url: http://10.0.0.2:8080
+tls:
+  options:
+    modern:
+      minVersion: VersionTLS12
+"#,
+        );
+        let new = config(
+            r#"
+tls:
+  options:
+    modern:
+      minVersion: VersionTLS13
+http:
+  services:
+    api:
+      loadBalancer:
+        servers:
+          - url: http://10.0.0.1:8080
+          - url: http://10.0.0.3:8080
+  routers:
+    api:
+      service: api
+      entryPoints: [web, websecure]
+      rule: "Host(`api.example.com`)"
+    admin:
+      rule: "Host(`admin.example.com`)"
+      service: api
+"#,
+        );
+
+        let diff = RouteDiff::between("v1.0.0", &old, "v1.1.0", &new);
+        let summary: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|c| (c.change, c.section.as_str(), c.name.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Added, "routers", "admin"),
+                (ChangeKind::Changed, "routers", "api"),
+                (ChangeKind::Removed, "routers", "legacy"),
+                (ChangeKind::Changed, "services", "api"),
+                (ChangeKind::Changed, "options", "modern"),
+            ]
+        );
+        assert_eq!(diff.changes[1].removed, vec!["entryPoints: [web]"]);
+        assert_eq!(diff.changes[1].added, vec!["entryPoints: [web, websecure]"]);
+        assert_eq!(
+            diff.changes[3].added,
+            vec!["loadBalancer.servers[].url: http://10.0.0.3:8080"]
+        );
+
+        let text = diff.to_string();
+        assert!(text.contains("+ http router 'admin'\n    + rule: Host(`admin.example.com`)"));
+        assert!(text.contains("~ TLS option 'modern'\n    - minVersion: VersionTLS12"));
+
+        let same = RouteDiff::between("v1.1.0", &new, "v1.1.0", &new);
+        assert!(same.changes.is_empty());
+        assert_eq!(
+            same.to_string(),
+            "No routing changes from v1.1.0 to v1.1.0\n"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Snapshot of which config version is live versus intended.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub project: String,
+    pub mount_path: String,
+    /// Tag the `current` symlink points at.
+    pub current_tag: Option<String>,
+    /// Directory the `current` symlink resolves to.
+    pub current_target: Option<String>,
+    /// Volume source the compose file declares for `mount_path`.
+    pub compose_source: Option<String>,
+    pub containers: Vec<ContainerStatus>,
+}
+
+/// What a single running container actually has mounted at `mount_path`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainerStatus {
+    pub name: String,
+    pub service: String,
+    pub mount_source: Option<String>,
+    pub config_tag: Option<String>,
+    /// True when the container is not running the config `current` points at.
+    pub stale: bool,
+}
+
+impl StatusReport {
+    pub fn stale_containers(&self) -> impl Iterator<Item = &ContainerStatus> {
+        self.containers.iter().filter(|c| c.stale)
+    }
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Project:        {}", self.project)?;
+        match (&self.current_tag, &self.current_target) {
+            (Some(tag), Some(target)) => writeln!(f, "Current:        {} ({})", tag, target)?,
+            (None, Some(target)) => writeln!(f, "Current:        {}", target)?,
+            _ => writeln!(f, "Current:        <none>")?,
+        }
+        writeln!(
+            f,
+            "Compose source: {} -> {}",
+            self.compose_source.as_deref().unwrap_or("<not set>"),
+            self.mount_path
+        )?;
+
+        if self.containers.is_empty() {
+            return writeln!(f, "No running containers found");
+        }
+
+        writeln!(f)?;
+        let name_width = self
+            .containers
+            .iter()
+            .map(|c| c.name.len())
+            .chain(["CONTAINER".len()])
+            .max()
+            .unwrap_or_default();
+        let service_width = self
+            .containers
+            .iter()
+            .map(|c| c.service.len())
+            .chain(["SERVICE".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:<nw$}  {:<sw$}  {:<8}  MOUNT",
+            "CONTAINER",
+            "SERVICE",
+            "STATE",
+            nw = name_width,
+            sw = service_width
+        )?;
+        for container in &self.containers {
+            writeln!(
+                f,
+                "{:<nw$}  {:<sw$}  {:<8}  {}",
+                container.name,
+                container.service,
+                if container.stale { "STALE" } else { "ok" },
+                container.mount_source.as_deref().unwrap_or("<not mounted>"),
+                nw = name_width,
+                sw = service_width
+            )?;
+        }
+        Ok(())
+    }
+}
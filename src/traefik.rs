@@ -0,0 +1,865 @@
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Router rule matchers Traefik v2 and v3 understand, lowercased.
+const HTTP_MATCHERS: &[&str] = &[
+    "clientip",
+    "header",
+    "headerregexp",
+    "headers",
+    "headersregexp",
+    "host",
+    "hostheader",
+    "hostregexp",
+    "method",
+    "path",
+    "pathprefix",
+    "pathregexp",
+    "query",
+    "queryregexp",
+];
+const TCP_MATCHERS: &[&str] = &["alpn", "clientip", "hostsni", "hostsniregexp"];
+
+const HTTP_MIDDLEWARES: &[&str] = &[
+    "addprefix",
+    "basicauth",
+    "buffering",
+    "chain",
+    "circuitbreaker",
+    "compress",
+    "contenttype",
+    "digestauth",
+    "errors",
+    "forwardauth",
+    "grpcweb",
+    "headers",
+    "inflightreq",
+    "ipallowlist",
+    "ipwhitelist",
+    "passtlsclientcert",
+    "plugin",
+    "ratelimit",
+    "redirectregex",
+    "redirectscheme",
+    "replacepath",
+    "replacepathregex",
+    "retry",
+    "stripprefix",
+    "stripprefixregex",
+];
+const TCP_MIDDLEWARES: &[&str] = &["inflightconn", "ipallowlist", "ipwhitelist"];
+
+/// What a protocol section (`http`, `tcp` or `udp`) may contain.
+struct Protocol {
+    name: &'static str,
+    sections: &'static [&'static str],
+    router_keys: &'static [&'static str],
+    /// Matchers allowed in router rules; `None` when routers have no rule.
+    matchers: Option<&'static [&'static str]>,
+    service_kinds: &'static [&'static str],
+    /// Key naming each load-balanced server's endpoint.
+    server_key: &'static str,
+    middlewares: &'static [&'static str],
+}
+
+const PROTOCOLS: &[Protocol] = &[
+    Protocol {
+        name: "http",
+        sections: &["routers", "services", "middlewares", "serverstransports"],
+        router_keys: &[
+            "entrypoints",
+            "middlewares",
+            "observability",
+            "priority",
+            "rule",
+            "rulesyntax",
+            "service",
+            "tls",
+        ],
+        matchers: Some(HTTP_MATCHERS),
+        service_kinds: &["failover", "loadbalancer", "mirroring", "weighted"],
+        server_key: "url",
+        middlewares: HTTP_MIDDLEWARES,
+    },
+    Protocol {
+        name: "tcp",
+        sections: &["routers", "services", "middlewares", "serverstransports"],
+        router_keys: &[
+            "entrypoints",
+            "middlewares",
+            "priority",
+            "rule",
+            "rulesyntax",
+            "service",
+            "tls",
+        ],
+        matchers: Some(TCP_MATCHERS),
+        service_kinds: &["loadbalancer", "weighted"],
+        server_key: "address",
+        middlewares: TCP_MIDDLEWARES,
+    },
+    Protocol {
+        name: "udp",
+        sections: &["routers", "services"],
+        router_keys: &["entrypoints", "service"],
+        matchers: None,
+        service_kinds: &["loadbalancer", "weighted"],
+        server_key: "address",
+        middlewares: &[],
+    },
+];
+
+/// A router, service, middleware or TLS option and the file that defines it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub file: PathBuf,
+    pub value: Value,
+}
+
+/// Identifies a definition: `("http", "routers", "api")`, `("tls", "options", "default")`.
+pub type Key = (String, String, String);
+
+/// The Traefik dynamic configuration in a directory, merged across its files the way
+/// the file provider merges them.
+#[derive(Debug, Default)]
+pub struct DynamicConfig {
+    pub definitions: BTreeMap<Key, Definition>,
+    /// Parse and structure errors, each prefixed with the file it was found in.
+    pub problems: Vec<String>,
+}
+
+impl DynamicConfig {
+    /// Reads every `.yml`, `.yaml` and `.toml` file under `dir`, skipping hidden entries
+    /// such as `.git`.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)?;
+        files.sort();
+
+        let mut config = Self::default();
+        for file in files {
+            let content = std::fs::read_to_string(&file).map_err(|e| Error::io(&file, e))?;
+            let relative = file.strip_prefix(dir).unwrap_or(&file).to_path_buf();
+            config.add_file(&relative, &content);
+        }
+        Ok(config)
+    }
+
+    /// Parses one file and merges its definitions in.
+    pub fn add_file(&mut self, file: &Path, content: &str) {
+        let is_toml = file.extension().is_some_and(|ext| ext == "toml");
+        let parsed = if is_toml {
+            toml::from_str::<Value>(content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str::<Value>(content).map_err(|e| e.to_string())
+        };
+        let root = match parsed {
+            Ok(Value::Null) => return,
+            Ok(Value::Mapping(root)) => root,
+            Ok(_) => return self.problem(file, "top level is not a mapping"),
+            Err(e) => return self.problem(file, format!("cannot parse: {}", e.trim())),
+        };
+
+        for (key, value) in &root {
+            let top = key_name(key);
+            if top == "tls" {
+                self.add_tls(file, value);
+                continue;
+            }
+            let Some(protocol) = PROTOCOLS.iter().find(|p| p.name == top) else {
+                self.problem(file, format!("unknown top-level key '{}'", key_str(key)));
+                continue;
+            };
+            let Some(sections) = self.mapping(file, value, protocol.name) else {
+                continue;
+            };
+            for (section, entries) in sections {
+                let section_name = key_name(section);
+                if !protocol.sections.contains(&section_name.as_str()) {
+                    self.problem(
+                        file,
+                        format!("unknown key '{}.{}'", protocol.name, key_str(section)),
+                    );
+                    continue;
+                }
+                let what = format!("{}.{}", protocol.name, key_str(section));
+                if let Some(entries) = self.mapping(file, entries, &what) {
+                    for (name, value) in entries {
+                        self.define(file, protocol.name, &section_name, name, value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_tls(&mut self, file: &Path, value: &Value) {
+        let Some(tls) = self.mapping(file, value, "tls") else {
+            return;
+        };
+        for (key, value) in tls {
+            match key_name(key).as_str() {
+                "options" | "stores" => {
+                    let section = key_name(key);
+                    let what = format!("tls.{}", key_str(key));
+                    if let Some(entries) = self.mapping(file, value, &what) {
+                        for (name, value) in entries {
+                            self.define(file, "tls", &section, name, value);
+                        }
+                    }
+                }
+                "certificates" => {
+                    if !value.is_sequence() {
+                        self.problem(file, "tls.certificates is not a list");
+                    }
+                }
+                _ => self.problem(file, format!("unknown key 'tls.{}'", key_str(key))),
+            }
+        }
+    }
+
+    fn define(&mut self, file: &Path, protocol: &str, section: &str, name: &Value, value: &Value) {
+        let key = (protocol.to_string(), section.to_string(), key_str(name));
+        if let Some(existing) = self.definitions.get(&key) {
+            if existing.value != *value {
+                let message = format!(
+                    "{} '{}' is also defined differently in {}",
+                    describe(protocol, section),
+                    key.2,
+                    existing.file.display()
+                );
+                self.problem(file, message);
+            }
+            return;
+        }
+        self.definitions.insert(
+            key,
+            Definition {
+                file: file.to_path_buf(),
+                value: value.clone(),
+            },
+        );
+    }
+
+    fn mapping<'a>(&mut self, file: &Path, value: &'a Value, what: &str) -> Option<&'a Mapping> {
+        match value {
+            Value::Mapping(mapping) => Some(mapping),
+            Value::Null => None,
+            _ => {
+                self.problem(file, format!("{} is not a mapping", what));
+                None
+            }
+        }
+    }
+
+    fn problem(&mut self, file: &Path, message: impl std::fmt::Display) {
+        self.problems
+            .push(format!("{}: {}", file.display(), message));
+    }
+
+    /// Names defined in `protocol.section`.
+    fn names(&self, protocol: &str, section: &str) -> Vec<&str> {
+        self.definitions
+            .keys()
+            .filter(|(p, s, _)| p == protocol && s == section)
+            .map(|(_, _, name)| name.as_str())
+            .collect()
+    }
+
+    /// Checks the structure of every router, service and middleware, and that every
+    /// name they refer to is defined here or qualified with an `@provider` suffix.
+    /// Returns the problems found, parse errors included.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = self.problems.clone();
+        for ((protocol_name, section, name), definition) in &self.definitions {
+            let Some(protocol) = PROTOCOLS.iter().find(|p| p.name == protocol_name) else {
+                continue;
+            };
+            let mut check = Check {
+                config: self,
+                protocol,
+                what: format!("{} '{}'", describe(protocol.name, section), name),
+                problems: Vec::new(),
+            };
+            let Some(fields) = definition.value.as_mapping() else {
+                check.problem("is not a mapping");
+                problems.extend(prefixed(&definition.file, check.problems));
+                continue;
+            };
+            match section.as_str() {
+                "routers" => check.router(fields),
+                "services" => check.service(fields),
+                "middlewares" => check.middleware(fields),
+                _ => {}
+            }
+            problems.extend(prefixed(&definition.file, check.problems));
+        }
+        problems
+    }
+}
+
+/// Loads the dynamic configuration under `dir` and fails with every problem found.
+pub fn validate_dir(dir: &Path) -> Result<()> {
+    let problems = DynamicConfig::load(dir)?.validate();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::TraefikConfig {
+            path: dir.to_path_buf(),
+            problems,
+        })
+    }
+}
+
+/// What Traefik's log says about the file provider reloading its directory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReloadLog {
+    /// No reload logged yet.
+    Pending,
+    /// The file provider's new configuration was received.
+    Loaded,
+    /// Traefik logged an error about the file provider or one of its routers; holds
+    /// the log line.
+    Failed(String),
+}
+
+/// Scans Traefik log output for the file provider picking up a change. Traefik only
+/// logs `Configuration received` at debug level, while errors are logged at any level.
+/// Handles the common, v3 console and JSON log formats.
+pub fn scan_reload_log(logs: &str) -> ReloadLog {
+    let mut loaded = false;
+    for line in logs.lines() {
+        let lower = line.to_ascii_lowercase();
+        let from_file = [
+            "providername=file",
+            "\"providername\":\"file\"",
+            "provider file",
+            "@file",
+        ]
+        .iter()
+        .any(|marker| lower.contains(marker));
+        if !from_file {
+            continue;
+        }
+        if lower.contains("level=error")
+            || lower.contains("\"level\":\"error\"")
+            || line.contains(" ERR ")
+        {
+            return ReloadLog::Failed(line.trim().to_string());
+        }
+        if lower.contains("configuration received") {
+            loaded = true;
+        }
+    }
+    if loaded {
+        ReloadLog::Loaded
+    } else {
+        ReloadLog::Pending
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).map_err(|e| Error::io(dir, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::io(dir, e))?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "yml" || ext == "yaml" || ext == "toml")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn prefixed(file: &Path, problems: Vec<String>) -> impl Iterator<Item = String> + '_ {
+    problems
+        .into_iter()
+        .map(move |problem| format!("{}: {}", file.display(), problem))
+}
+
+/// Names the kind of a definition for messages, e.g. `http router` or `TLS option`.
+pub fn describe(protocol: &str, section: &str) -> String {
+    let kind = match section {
+        "routers" => "router",
+        "services" => "service",
+        "middlewares" => "middleware",
+        "serverstransports" => "serversTransport",
+        "options" => "option",
+        "stores" => "store",
+        other => other,
+    };
+    if protocol == "tls" {
+        format!("TLS {}", kind)
+    } else {
+        format!("{} {}", protocol, kind)
+    }
+}
+
+fn key_str(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// Traefik matches configuration keys case-insensitively.
+fn key_name(key: &Value) -> String {
+    key_str(key).to_ascii_lowercase()
+}
+
+fn field<'a>(fields: &'a Mapping, name: &str) -> Option<&'a Value> {
+    fields
+        .iter()
+        .find(|(key, _)| key_name(key) == name)
+        .map(|(_, value)| value)
+}
+
+/// Structure and reference checks for one definition.
+struct Check<'a> {
+    config: &'a DynamicConfig,
+    protocol: &'a Protocol,
+    what: String,
+    problems: Vec<String>,
+}
+
+impl Check<'_> {
+    fn problem(&mut self, message: impl std::fmt::Display) {
+        self.problems.push(format!("{} {}", self.what, message));
+    }
+
+    fn router(&mut self, fields: &Mapping) {
+        for (key, _) in fields {
+            if !self.protocol.router_keys.contains(&key_name(key).as_str()) {
+                self.problem(format!("has unknown key '{}'", key_str(key)));
+            }
+        }
+        if let Some(matchers) = self.protocol.matchers {
+            match field(fields, "rule") {
+                Some(Value::String(rule)) => {
+                    if let Err(e) = check_rule(rule, matchers) {
+                        self.problem(format!("has an invalid rule: {}", e));
+                    }
+                }
+                Some(_) => self.problem("has a rule that is not a string"),
+                None => self.problem("has no rule"),
+            }
+        }
+        match field(fields, "service") {
+            Some(Value::String(service)) => self.reference("services", "service", service),
+            Some(_) => self.problem("has a service that is not a string"),
+            None => self.problem("has no service"),
+        }
+        if let Some(middlewares) = field(fields, "middlewares") {
+            self.references(middlewares, "middlewares", "middleware");
+        }
+        if let Some(entrypoints) = field(fields, "entrypoints") {
+            if !is_string_list(entrypoints) {
+                self.problem("has entryPoints that are not a list of names");
+            }
+        }
+        if let Some(priority) = field(fields, "priority") {
+            if !priority.is_i64() && !priority.is_u64() {
+                self.problem("has a priority that is not an integer");
+            }
+        }
+    }
+
+    fn service(&mut self, fields: &Mapping) {
+        let [(kind, spec)] = fields.iter().collect::<Vec<_>>()[..] else {
+            return self.problem(format!(
+                "must have exactly one of {}",
+                self.protocol.service_kinds.join(", ")
+            ));
+        };
+        let kind_name = key_name(kind);
+        if !self.protocol.service_kinds.contains(&kind_name.as_str()) {
+            return self.problem(format!("has unknown type '{}'", key_str(kind)));
+        }
+        let Some(spec) = spec.as_mapping() else {
+            return self.problem(format!("{} is not a mapping", key_str(kind)));
+        };
+        match kind_name.as_str() {
+            "loadbalancer" => self.servers(spec),
+            "weighted" => {
+                self.named_list(spec, "services");
+            }
+            "mirroring" => {
+                self.service_field(spec, "service", true);
+                self.named_list(spec, "mirrors");
+            }
+            "failover" => {
+                self.service_field(spec, "service", true);
+                self.service_field(spec, "fallback", true);
+            }
+            _ => {}
+        }
+    }
+
+    fn servers(&mut self, spec: &Mapping) {
+        let Some(servers) = field(spec, "servers") else {
+            return self.problem("loadBalancer has no servers");
+        };
+        let Some(servers) = servers.as_sequence() else {
+            return self.problem("loadBalancer servers is not a list");
+        };
+        let key = self.protocol.server_key;
+        for (i, server) in servers.iter().enumerate() {
+            let endpoint = server.as_mapping().and_then(|server| field(server, key));
+            if !endpoint.is_some_and(Value::is_string) {
+                self.problem(format!("server #{} has no {}", i + 1, key));
+            }
+        }
+    }
+
+    /// A list of `{name: <service>, ...}` entries, as in `weighted.services`.
+    fn named_list(&mut self, spec: &Mapping, list: &str) {
+        let Some(entries) = field(spec, list).and_then(Value::as_sequence) else {
+            return self.problem(format!("has no {} list", list));
+        };
+        for entry in entries {
+            match entry.as_mapping().and_then(|entry| field(entry, "name")) {
+                Some(Value::String(name)) => self.reference("services", "service", name),
+                _ => self.problem(format!("has a {} entry without a name", list)),
+            }
+        }
+    }
+
+    fn service_field(&mut self, spec: &Mapping, name: &str, required: bool) {
+        match field(spec, name) {
+            Some(Value::String(service)) => self.reference("services", "service", service),
+            Some(_) => self.problem(format!("has a {} that is not a string", name)),
+            None if required => self.problem(format!("has no {}", name)),
+            None => {}
+        }
+    }
+
+    fn middleware(&mut self, fields: &Mapping) {
+        let [(kind, spec)] = fields.iter().collect::<Vec<_>>()[..] else {
+            return self.problem("must have exactly one middleware type");
+        };
+        let kind_name = key_name(kind);
+        if !self.protocol.middlewares.contains(&kind_name.as_str()) {
+            return self.problem(format!("has unknown type '{}'", key_str(kind)));
+        }
+        let spec = spec.as_mapping();
+        match kind_name.as_str() {
+            "chain" => match spec.and_then(|spec| field(spec, "middlewares")) {
+                Some(middlewares) => self.references(middlewares, "middlewares", "middleware"),
+                None => self.problem("chain has no middlewares"),
+            },
+            "errors" => {
+                if let Some(spec) = spec {
+                    self.service_field(spec, "service", false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn references(&mut self, names: &Value, section: &str, kind: &str) {
+        if !is_string_list(names) {
+            return self.problem(format!("has {} that are not a list of names", section));
+        }
+        for name in names.as_sequence().into_iter().flatten() {
+            if let Some(name) = name.as_str() {
+                self.reference(section, kind, name);
+            }
+        }
+    }
+
+    /// Names qualified with `@provider` live in another provider and aren't checked.
+    fn reference(&mut self, section: &str, kind: &str, name: &str) {
+        if name.contains('@')
+            || self
+                .config
+                .names(self.protocol.name, section)
+                .contains(&name)
+        {
+            return;
+        }
+        self.problem(format!("refers to undefined {} '{}'", kind, name));
+    }
+}
+
+fn is_string_list(value: &Value) -> bool {
+    value
+        .as_sequence()
+        .is_some_and(|items| items.iter().all(Value::is_string))
+}
+
+/// Parses a router rule such as ``Host(`a.com`) && (PathPrefix(`/api`) || !Method(`GET`))``.
+fn check_rule(rule: &str, matchers: &[&str]) -> std::result::Result<(), String> {
+    let mut parser = RuleParser {
+        chars: rule.char_indices().peekable(),
+        matchers,
+    };
+    parser.expression()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(()),
+        Some((i, c)) => Err(format!("unexpected '{}' at position {}", c, i)),
+    }
+}
+
+struct RuleParser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    matchers: &'a [&'a str],
+}
+
+impl RuleParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expression(&mut self) -> std::result::Result<(), String> {
+        self.term()?;
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek().map(|&(_, c)| c) {
+                Some(op @ ('&' | '|')) => {
+                    self.chars.next();
+                    match self.chars.next() {
+                        Some((_, c)) if c == op => {}
+                        _ => return Err(format!("expected '{}{}'", op, op)),
+                    }
+                    self.term()?;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn term(&mut self) -> std::result::Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some((_, '!')) => {
+                self.chars.next();
+                self.term()
+            }
+            Some((i, '(')) => {
+                self.chars.next();
+                self.expression()?;
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some((_, ')')) => Ok(()),
+                    _ => Err(format!("unclosed '(' at position {}", i)),
+                }
+            }
+            Some((i, c)) if c.is_ascii_alphabetic() => self.matcher(i),
+            Some((i, c)) => Err(format!("unexpected '{}' at position {}", c, i)),
+            None => Err("unexpected end of rule".to_string()),
+        }
+    }
+
+    fn matcher(&mut self, start: usize) -> std::result::Result<(), String> {
+        let mut name = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric()) {
+            name.push(c);
+        }
+        if !self.matchers.contains(&name.to_ascii_lowercase().as_str()) {
+            return Err(format!("unknown matcher '{}' at position {}", name, start));
+        }
+        self.skip_whitespace();
+        if self.chars.next_if(|&(_, c)| c == '(').is_none() {
+            return Err(format!("expected '(' after {}", name));
+        }
+        loop {
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ')')) => return Ok(()),
+                Some((i, quote @ ('`' | '"' | '\''))) => {
+                    if !self.chars.any(|(_, c)| c == quote) {
+                        return Err(format!("unterminated string at position {}", i));
+                    }
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some((_, ',')) => {}
+                        Some((_, ')')) => return Ok(()),
+                        _ => return Err(format!("expected ',' or ')' in {}(...)", name)),
+                    }
+                }
+                _ => return Err(format!("expected a quoted value in {}(...)", name)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_rule() {
+        let ok = [
+            "Host(`example.com`)",
+            "Host(`a.com`, `b.com`) && (PathPrefix(`/api`) || !Method(`GET`))",
+            "HOST(\"a.com\")&&Path(`/`)",
+        ];
+        for rule in ok {
+            assert_eq!(check_rule(rule, HTTP_MATCHERS), Ok(()), "{}", rule);
+        }
+        let bad = [
+            ("Hots(`example.com`)", "unknown matcher 'Hots'"),
+            ("Host(`example.com`", "expected ',' or ')'"),
+            ("Host(`example.com)", "unterminated string"),
+            ("Host(`a`) & Path(`/`)", "expected '&&'"),
+            ("(Host(`a`)", "unclosed '('"),
+            ("Host(`a`) Path(`/`)", "unexpected 'P'"),
+            ("", "unexpected end"),
+        ];
+        for (rule, expected) in bad {
+            let err = check_rule(rule, HTTP_MATCHERS).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", rule, err);
+        }
+        assert!(check_rule("HostSNI(`*`)", TCP_MATCHERS).is_ok());
+    }
+
+    #[test]
+    fn test_scan_reload_log() {
+        assert_eq!(scan_reload_log(""), ReloadLog::Pending);
+        let v2 = r#"time="2024-05-01T10:00:00Z" level=debug msg="Configuration received from provider file: {\"http\":{}}" providerName=file"#;
+        assert_eq!(scan_reload_log(v2), ReloadLog::Loaded);
+        let v3 = "2024-05-01T10:00:00Z DBG github.com/traefik/traefik/v3/pkg/server/configurationwatcher.go:227 > Configuration received config={} providerName=file";
+        assert_eq!(scan_reload_log(v3), ReloadLog::Loaded);
+        let docker_only =
+            r#"{"level":"debug","providerName":"docker","message":"Configuration received"}"#;
+        assert_eq!(scan_reload_log(docker_only), ReloadLog::Pending);
+
+        let failed = format!(
+            "{}\n{}",
+            v2,
+            r#"{"level":"error","entryPointName":"web","routerName":"api@file","message":"the service \"missing@file\" does not exist"}"#
+        );
+        match scan_reload_log(&failed) {
+            ReloadLog::Failed(line) => assert!(line.contains("missing@file"), "{}", line),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_references_across_files() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git/config.yml"), "not: [traefik").unwrap();
+        std::fs::write(
+            dir.path().join("routers.yml"),
+            r#"
+http:
+  routers:
+    api:
+      rule: "Host(`api.example.com`)"
+      entryPoints: [websecure]
+      service: api
+      middlewares: [secure, auth@docker]
+    dashboard:
+      rule: "Host(`traefik.example.com`)"
+      service: api@internal
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("services.toml"),
+            r#"
+[http.services.api.loadBalancer]
+servers = [{ url = "http://10.0.0.1:8080" }]
+
+[http.middlewares.secure.headers]
+stsSeconds = 31536000
+
+[tls.options.modern]
+minVersion = "VersionTLS13"
+"#,
+        )
+        .unwrap();
+        validate_dir(dir.path()).unwrap();
+
+        let config = DynamicConfig::load(dir.path()).unwrap();
+        let key = (
+            "tls".to_string(),
+            "options".to_string(),
+            "modern".to_string(),
+        );
+        assert_eq!(
+            config.definitions[&key].file,
+            PathBuf::from("services.toml")
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let mut config = DynamicConfig::default();
+        config.add_file(
+            Path::new("bad.yml"),
+            r#"
+http:
+  routers:
+    api:
+      rule: "Hots(`api.example.com`)"
+      service: missing
+      middlewares: [nope]
+      entrypoint: [web]
+    norule:
+      service: api
+  services:
+    api:
+      loadBalancer:
+        servers:
+          - address: 10.0.0.1:80
+    both:
+      loadBalancer: {servers: []}
+      weighted: {services: [{name: api}]}
+  middlewares:
+    typo:
+      stripPrefixes: {prefixes: [/api]}
+    chained:
+      chain: {middlewares: [typo, ghost]}
+tcp:
+  routers:
+    db:
+      rule: "HostSNI(`*`)"
+      service: api
+htp: {}
+"#,
+        );
+        config.add_file(Path::new("broken.yaml"), "http: [unclosed");
+
+        let problems = config.validate();
+        let expected = [
+            "bad.yml: unknown top-level key 'htp'",
+            "broken.yaml: cannot parse",
+            "http router 'api' has an invalid rule: unknown matcher 'Hots'",
+            "http router 'api' refers to undefined service 'missing'",
+            "http router 'api' refers to undefined middleware 'nope'",
+            "http router 'api' has unknown key 'entrypoint'",
+            "http router 'norule' has no rule",
+            "http service 'api' server #1 has no url",
+            "http service 'both' must have exactly one of",
+            "http middleware 'typo' has unknown type 'stripPrefixes'",
+            "http middleware 'chained' refers to undefined middleware 'ghost'",
+            // TCP names are separate from HTTP ones
+            "tcp router 'db' refers to undefined service 'api'",
+        ];
+        for expected in expected {
+            assert!(
+                problems.iter().any(|p| p.contains(expected)),
+                "missing '{}' in {:#?}",
+                expected,
+                problems
+            );
+        }
+        assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+    }
+}
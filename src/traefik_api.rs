@@ -0,0 +1,330 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::error::{Error, Result};
+use crate::http::{self, Response};
+use crate::traefik::DynamicConfig;
+
+/// Upper bound for a single Traefik API request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Provider suffix Traefik gives everything loaded from the mounted directory.
+const FILE_PROVIDER: &str = "file";
+
+/// Normalizes the address of Traefik's API (`--traefik-api`) to `host:port`.
+/// Only plain HTTP is supported, as for the Docker socket.
+pub fn parse_address(address: &str) -> Result<String> {
+    let host = address
+        .strip_prefix("http://")
+        .unwrap_or(address)
+        .trim_end_matches('/');
+    let valid = !host.contains("://")
+        && !host.contains('/')
+        && host
+            .rsplit_once(':')
+            .is_some_and(|(name, port)| !name.is_empty() && port.parse::<u16>().is_ok());
+    if !valid {
+        return Err(Error::Config(format!(
+            "TRAEFIK_API must be host:port or http://host:port, got '{}'",
+            address
+        )));
+    }
+    Ok(host.to_string())
+}
+
+/// A router Traefik should report once it has loaded a config version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedRouter {
+    /// `http`, `tcp` or `udp`.
+    pub protocol: String,
+    /// Qualified name as the API reports it, e.g. `api@file`.
+    pub name: String,
+    pub rule: Option<String>,
+}
+
+/// The routers defined in a config version, as Traefik's file provider names them.
+pub fn expected_routers(config: &DynamicConfig) -> Vec<ExpectedRouter> {
+    config
+        .definitions
+        .iter()
+        .filter(|((protocol, section, _), _)| protocol != "tls" && section == "routers")
+        .map(|((protocol, _, name), definition)| ExpectedRouter {
+            protocol: protocol.clone(),
+            name: format!("{}@{}", name, FILE_PROVIDER),
+            rule: definition
+                .value
+                .as_mapping()
+                .and_then(|fields| {
+                    fields.iter().find(|(key, _)| {
+                        key.as_str().is_some_and(|k| k.eq_ignore_ascii_case("rule"))
+                    })
+                })
+                .and_then(|(_, rule)| rule.as_str())
+                .map(str::to_string),
+        })
+        .collect()
+}
+
+/// A router as reported by the API.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct RouterState {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub rule: Option<String>,
+    /// `enabled`, `disabled` or `warning`.
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub error: Vec<String>,
+}
+
+/// The parts of `/api/rawdata` that are checked.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawData {
+    #[serde(default)]
+    routers: HashMap<String, RouterState>,
+    #[serde(default)]
+    tcp_routers: HashMap<String, RouterState>,
+    #[serde(default)]
+    udp_routers: HashMap<String, RouterState>,
+}
+
+/// Client for Traefik's API, used to confirm that a recreated instance actually
+/// loaded the new routers.
+#[derive(Debug, Clone)]
+pub struct TraefikApi {
+    address: String,
+    timeout: Duration,
+}
+
+impl TraefikApi {
+    /// `address` is `host:port`, as returned by [`parse_address`].
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Sends one GET over a fresh connection. Failures are described as text, since
+    /// the caller keeps polling until its own timeout.
+    async fn get(&self, endpoint: &str) -> std::result::Result<Response, String> {
+        let exchange = async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+            let request = http::encode_request("GET", endpoint, &self.address, None);
+            stream.write_all(&request).await?;
+            http::read_response(&mut BufReader::new(stream), "GET").await
+        };
+        match tokio::time::timeout(self.timeout, exchange).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => Err(format!("GET {} failed: {}", endpoint, e)),
+            Err(_) => Err(format!(
+                "GET {} timed out after {}s",
+                endpoint,
+                self.timeout.as_secs_f32()
+            )),
+        }
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> std::result::Result<Option<T>, String> {
+        let response = self.get(endpoint).await?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.is_success() {
+            return Err(format!(
+                "GET {} returned {} {}",
+                endpoint, response.status, response.reason
+            ));
+        }
+        serde_json::from_slice(&response.body)
+            .map(Some)
+            .map_err(|e| format!("GET {} returned unexpected JSON: {}", endpoint, e))
+    }
+
+    /// Every router Traefik knows, keyed by `(protocol, qualified name)`. Reads
+    /// `/api/rawdata`, falling back to the per-protocol router lists
+    /// (`/api/http/routers`, ...) where rawdata isn't served.
+    pub async fn routers(
+        &self,
+    ) -> std::result::Result<HashMap<(String, String), RouterState>, String> {
+        let mut routers = HashMap::new();
+        if let Some(raw) = self.get_json::<RawData>("/api/rawdata").await? {
+            for (protocol, states) in [
+                ("http", raw.routers),
+                ("tcp", raw.tcp_routers),
+                ("udp", raw.udp_routers),
+            ] {
+                for (name, mut state) in states {
+                    state.name.clone_from(&name);
+                    routers.insert((protocol.to_string(), name), state);
+                }
+            }
+            return Ok(routers);
+        }
+        for protocol in ["http", "tcp", "udp"] {
+            let endpoint = format!("/api/{}/routers?per_page=10000", protocol);
+            let states = self
+                .get_json::<Vec<RouterState>>(&endpoint)
+                .await?
+                .ok_or_else(|| format!("GET {} returned 404; is the API enabled?", endpoint))?;
+            for state in states {
+                routers.insert((protocol.to_string(), state.name.clone()), state);
+            }
+        }
+        Ok(routers)
+    }
+
+    /// Checks that every expected router is loaded with its new rule, `enabled` and
+    /// without errors. Returns what is still wrong.
+    pub async fn check(&self, expected: &[ExpectedRouter]) -> std::result::Result<(), String> {
+        let routers = self.routers().await?;
+        let problems: Vec<String> = expected
+            .iter()
+            .filter_map(|router| {
+                let key = (router.protocol.clone(), router.name.clone());
+                let Some(state) = routers.get(&key) else {
+                    return Some(format!(
+                        "{} router '{}' is not loaded",
+                        router.protocol, router.name
+                    ));
+                };
+                if !state.error.is_empty() {
+                    return Some(format!(
+                        "{} router '{}' has errors: {}",
+                        router.protocol,
+                        router.name,
+                        state.error.join("; ")
+                    ));
+                }
+                if state.status != "enabled" {
+                    return Some(format!(
+                        "{} router '{}' is {}",
+                        router.protocol, router.name, state.status
+                    ));
+                }
+                if router.rule.is_some() && state.rule != router.rule {
+                    return Some(format!(
+                        "{} router '{}' still has rule {}",
+                        router.protocol,
+                        router.name,
+                        state.rule.as_deref().unwrap_or("<none>")
+                    ));
+                }
+                None
+            })
+            .collect();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Answers each request with the body `route` returns for its path, or 404.
+    async fn serve(route: fn(&str) -> Option<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let read = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                let response = match route(path) {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        address
+    }
+
+    fn expected() -> Vec<ExpectedRouter> {
+        let mut config = DynamicConfig::default();
+        config.add_file(
+            Path::new("routes.yml"),
+            "http:\n  routers:\n    api:\n      rule: \"Host(`api.example.com`)\"\n      service: api\ntcp:\n  routers:\n    db:\n      rule: \"HostSNI(`*`)\"\n      service: db\n",
+        );
+        expected_routers(&config)
+    }
+
+    #[tokio::test]
+    async fn test_check_against_rawdata() {
+        let address = serve(|path| {
+            (path == "/api/rawdata").then(|| {
+                r#"{"routers": {"api@file": {"rule": "Host(`api.example.com`)", "status": "enabled", "service": "api"},
+                                "dashboard@internal": {"status": "enabled"}},
+                    "tcpRouters": {"db@file": {"rule": "HostSNI(`*`)", "status": "disabled", "error": ["the service \"db@file\" does not exist"]}}}"#
+                    .to_string()
+            })
+        })
+        .await;
+        let api = TraefikApi::new(address);
+        let err = api.check(&expected()).await.unwrap_err();
+        assert_eq!(
+            err,
+            "tcp router 'db@file' has errors: the service \"db@file\" does not exist"
+        );
+        assert!(api.check(&expected()[..1]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_falls_back_to_router_lists() {
+        let address = serve(|path| match path.split('?').next().unwrap() {
+            "/api/http/routers" => Some(
+                r#"[{"name": "api@file", "rule": "Host(`old.example.com`)", "status": "enabled"}]"#
+                    .to_string(),
+            ),
+            "/api/tcp/routers" | "/api/udp/routers" => Some("[]".to_string()),
+            _ => None,
+        })
+        .await;
+        let err = TraefikApi::new(address)
+            .check(&expected())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "http router 'api@file' still has rule Host(`old.example.com`), tcp router 'db@file' is not loaded"
+        );
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("http://127.0.0.1:8080/").unwrap(),
+            "127.0.0.1:8080"
+        );
+        assert_eq!(parse_address("traefik:8080").unwrap(), "traefik:8080");
+        for bad in ["https://traefik:8080", "traefik", "http://traefik:8080/api"] {
+            assert_eq!(parse_address(bad).unwrap_err().exit_code(), 2, "{}", bad);
+        }
+    }
+}
@@ -122,3 +122,36 @@ pub struct Mount {
     #[serde(rename = "Propagation", alias = "Propagation", alias = "propagation")]
     pub propagation: String,
 }
+
+/// Subset of `GET /containers/{id}/json` needed to judge container health.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerInspect {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "State")]
+    pub state: ContainerState,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerState {
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Running")]
+    pub running: bool,
+    #[serde(rename = "ExitCode")]
+    pub exit_code: i64,
+    #[serde(rename = "StartedAt")]
+    pub started_at: String,
+    #[serde(rename = "Health")]
+    pub health: Option<Health>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "FailingStreak", default)]
+    pub failing_streak: i64,
+}
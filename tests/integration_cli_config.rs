@@ -1,4 +1,5 @@
-use rolling_deployer::cli::{deploy, CLI};
+use clap::Parser;
+use rolling_deployer::cli::{execute, CLI};
 use std::fs::File;
 use std::io::Write;
 use tempfile::tempdir;
@@ -29,24 +30,28 @@ async fn test_cli_precedence_over_env() {
     // Create the compose file expected by the test
     File::create(&compose_file_path).unwrap();
 
-    let cli = CLI {
-        tag: "v1.2.3".to_string(),
-        name: Some("cli_name".to_string()),
-        socket_path: temp_dir
-            .path()
-            .join("cli_socket.sock")
-            .display()
-            .to_string(),
-        repo_url: Some(TEST_REPO_URL.to_string()),
-        clone_path: Some(clone_path.display().to_string()),
-        mount_path: Some(mount_path.display().to_string()),
-        verbose: 0,
-        compose_file: compose_file_path.display().to_string(),
-        env_file: env_path.display().to_string(),
-        swarm: false,
-    };
-
-    deploy(cli).await;
+    let socket_path = temp_dir.path().join("cli_socket.sock");
+    let cli = CLI::parse_from([
+        "rolling-deployer",
+        "deploy",
+        "v1.2.3",
+        "--name",
+        "cli_name",
+        "--socket-path",
+        &socket_path.display().to_string(),
+        "--repo-url",
+        TEST_REPO_URL,
+        "--clone-path",
+        &clone_path.display().to_string(),
+        "--mount-path",
+        &mount_path.display().to_string(),
+        "--compose-file",
+        &compose_file_path.display().to_string(),
+        "--env-file",
+        &env_path.display().to_string(),
+    ]);
+
+    execute(cli).await.unwrap();
 }
 
 #[tokio::test]
@@ -83,20 +88,17 @@ async fn test_env_used_when_cli_missing() {
     // Also create the compose file at the path specified in the env (for completeness)
     File::create(&compose_file_path).unwrap();
 
-    let cli = CLI {
-        tag: "v1.2.3".to_string(),
-        name: None,
-        socket_path: "/var/run/docker.sock".to_string(),
-        repo_url: None,
-        clone_path: None,
-        mount_path: None,
-        verbose: 0,
-        compose_file: compose_file_path.display().to_string(),
-        env_file: env_path.display().to_string(),
-        swarm: false,
-    };
-
-    deploy(cli).await;
+    let cli = CLI::parse_from([
+        "rolling-deployer",
+        "deploy",
+        "v1.2.3",
+        "--compose-file",
+        &compose_file_path.display().to_string(),
+        "--env-file",
+        &env_path.display().to_string(),
+    ]);
+
+    execute(cli).await.unwrap();
 }
 
 #[tokio::test]
@@ -109,20 +111,22 @@ async fn test_default_used_when_none_set() {
     File::create(&env_path).unwrap(); // empty .env
     File::create(&compose_file_path).unwrap();
 
-    let cli = CLI {
-        tag: "v1.2.3".to_string(),
-        name: None,
-        socket_path: "/var/run/docker.sock".to_string(),
-        repo_url: Some(TEST_REPO_URL.to_string()),
-        clone_path: None,
-        mount_path: Some(mount_path.display().to_string()), // required
-        verbose: 0,
-        compose_file: compose_file_path.display().to_string(),
-        env_file: env_path.display().to_string(),
-        swarm: false,
-    };
-
-    deploy(cli).await;
+    let cli = CLI::parse_from([
+        "rolling-deployer",
+        "deploy",
+        "v1.2.3",
+        "--repo-url",
+        TEST_REPO_URL,
+        // required
+        "--mount-path",
+        &mount_path.display().to_string(),
+        "--compose-file",
+        &compose_file_path.display().to_string(),
+        "--env-file",
+        &env_path.display().to_string(),
+    ]);
+
+    execute(cli).await.unwrap();
 }
 
 #[tokio::test]
@@ -142,18 +146,24 @@ async fn test_swarm_mode_flag() {
 
     File::create(&compose_file_path).unwrap();
 
-    let cli = CLI {
-        tag: "v1.2.3".to_string(),
-        name: Some("swarm_name".to_string()),
-        socket_path: "/var/run/docker.sock".to_string(),
-        repo_url: Some(TEST_REPO_URL.to_string()),
-        clone_path: Some(clone_path.display().to_string()),
-        mount_path: Some(mount_path.display().to_string()),
-        verbose: 0,
-        compose_file: compose_file_path.display().to_string(),
-        env_file: env_path.display().to_string(),
-        swarm: true,
-    };
-
-    deploy(cli).await;
+    let cli = CLI::parse_from([
+        "rolling-deployer",
+        "deploy",
+        "v1.2.3",
+        "--name",
+        "swarm_name",
+        "--repo-url",
+        TEST_REPO_URL,
+        "--clone-path",
+        &clone_path.display().to_string(),
+        "--mount-path",
+        &mount_path.display().to_string(),
+        "--compose-file",
+        &compose_file_path.display().to_string(),
+        "--env-file",
+        &env_path.display().to_string(),
+        "--swarm",
+    ]);
+
+    execute(cli).await.unwrap();
 }